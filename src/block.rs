@@ -4,6 +4,8 @@ use bevy::{math::vec3, prelude::*, utils::HashMap};
 use bevy_inspector_egui::Inspectable;
 use bevy_mod_picking::{Highlighting, PickableBundle};
 
+use crate::theme::Palette;
+
 #[derive(Component)]
 pub struct BlockMesh;
 
@@ -16,48 +18,64 @@ pub struct Block {
     pub goal: i32,
 }
 
-/// Spawn Mesh for blocks and return entity ids
-/// This function must be called in game setup
-pub fn spawn_meshes(
-    commands: &mut Commands,
-    size: usize,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
-) -> HashMap<(usize, usize), Entity> {
-    let mut mesh = Mesh::from(shape::Cube { size: 1.0 });
-    let uv_modified = vec![
-        // +z
+/// Texture coordinates of the cube faces in the order +z, -z, +x, -x, +y, -y
+pub const FACE_UVS: [[[f32; 2]; 4]; 6] = [
+    // +z
+    [
         [1.0 / 4.0, 1.0],
         [2.0 / 4.0, 1.0],
         [2.0 / 4.0, 2.0 / 3.0],
         [1.0 / 4.0, 2.0 / 3.0],
-        // -z
+    ],
+    // -z
+    [
         [1.0 / 4.0, 1.0 / 3.0],
         [2.0 / 4.0, 1.0 / 3.0],
         [2.0 / 4.0, 0.0 / 3.0],
         [1.0 / 4.0, 0.0 / 3.0],
-        // +x
+    ],
+    // +x
+    [
         [3.0 / 4.0, 1.0 / 3.0],
         [2.0 / 4.0, 1.0 / 3.0],
         [2.0 / 4.0, 2.0 / 3.0],
         [3.0 / 4.0, 2.0 / 3.0],
-        // -x
+    ],
+    // -x
+    [
         [0.0 / 4.0, 2.0 / 3.0],
         [1.0 / 4.0, 2.0 / 3.0],
         [1.0 / 4.0, 1.0 / 3.0],
         [0.0 / 4.0, 1.0 / 3.0],
-        // +y
+    ],
+    // +y
+    [
         [2.0 / 4.0, 1.0 / 3.0],
         [1.0 / 4.0, 1.0 / 3.0],
         [1.0 / 4.0, 2.0 / 3.0],
         [2.0 / 4.0, 2.0 / 3.0],
-        // -y
+    ],
+    // -y
+    [
         [3.0 / 4.0, 2.0 / 3.0],
         [1.0, 2.0 / 3.0],
         [1.0, 1.0 / 3.0],
         [3.0 / 4.0, 1.0 / 3.0],
-    ];
+    ],
+];
+
+/// Spawn Mesh for blocks and return entity ids
+/// This function must be called in game setup
+pub fn spawn_meshes(
+    commands: &mut Commands,
+    size: usize,
+    palette: Palette,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+) -> HashMap<(usize, usize), Entity> {
+    let mut mesh = Mesh::from(shape::Cube { size: 1.0 });
+    let uv_modified: Vec<[f32; 2]> = FACE_UVS.iter().flatten().copied().collect();
     mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uv_modified);
     let cube_mesh = meshes.add(mesh);
 
@@ -69,7 +87,7 @@ pub fn spawn_meshes(
                     asset_server.load(format!("images/image{}.png", x + z * size + 1).as_str());
                 let material = materials.add(StandardMaterial {
                     base_color_texture: Some(texture.clone()),
-                    base_color: Color::hsl(360.0 * z as f32 / size as f32, 1.0, 0.6),
+                    base_color: palette.block_color(z, size),
                     ..default()
                 });
                 mesh_entities.insert(
@@ -1,13 +1,16 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, time::Duration};
 
-use bevy::prelude::*;
+use bevy::{input::touch::Touch, prelude::*, utils::HashMap};
 use bevy_mod_picking::PickingEvent;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    game::{GameError, GameState},
-    local_storage::LocalStorage,
-    player::{PlayLog, PlayerState},
-    ui::GameMode,
+    camera::CameraOrbit,
+    game::{GameError, GameState, MoveTimer},
+    network::{Move, NetworkChannel},
+    player::{PlayLog, PlayerIdentity, PlayerState},
+    settings::{Settings, SettingsChangedEvent},
+    ui::{shuffle, GameMode},
 };
 
 pub struct CustomInputPlugin;
@@ -17,7 +20,9 @@ impl Plugin for CustomInputPlugin {
         app.add_startup_system(setup_input).add_system_set(
             SystemSet::new()
                 .with_system(input_keyboard)
-                .with_system(input_click),
+                .with_system(input_click)
+                .with_system(input_gamepad)
+                .with_system(apply_input_settings),
         );
     }
 }
@@ -50,26 +55,161 @@ impl GameInput {
     }
 }
 
-const BUFFER_MAX: usize = 3;
+impl From<GameInput> for Move {
+    fn from(input: GameInput) -> Self {
+        match input {
+            GameInput::Up(_, _) => Move::Up,
+            GameInput::Down(_, _) => Move::Down,
+            GameInput::Left(_, _) => Move::Left,
+            GameInput::Right(_, _) => Move::Right,
+        }
+    }
+}
+
+/// Actions of the keyboard, each bound to any number of keys
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum InputAction {
+    Up,
+    Down,
+    Left,
+    Right,
+    Undo,
+    Redo,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 6] = [
+        InputAction::Up,
+        InputAction::Down,
+        InputAction::Left,
+        InputAction::Right,
+        InputAction::Undo,
+        InputAction::Redo,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputAction::Up => "Up",
+            InputAction::Down => "Down",
+            InputAction::Left => "Left",
+            InputAction::Right => "Right",
+            InputAction::Undo => "Undo",
+            InputAction::Redo => "Redo",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct KeyBindings(HashMap<InputAction, Vec<KeyCode>>);
+
+/// Arrow keys, WASD and vim-style hjkl all move blocks by default
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self(HashMap::from_iter([
+            (InputAction::Up, vec![KeyCode::Up, KeyCode::W, KeyCode::K]),
+            (
+                InputAction::Down,
+                vec![KeyCode::Down, KeyCode::S, KeyCode::J],
+            ),
+            (
+                InputAction::Left,
+                vec![KeyCode::Left, KeyCode::A, KeyCode::H],
+            ),
+            (
+                InputAction::Right,
+                vec![KeyCode::Right, KeyCode::D, KeyCode::L],
+            ),
+            (InputAction::Undo, vec![KeyCode::Z]),
+            (InputAction::Redo, vec![KeyCode::X]),
+        ]))
+    }
+}
+
+impl KeyBindings {
+    pub fn keys(&self, action: InputAction) -> &[KeyCode] {
+        self.0.get(&action).map_or(&[], |keys| keys.as_slice())
+    }
+
+    pub fn action(&self, key: KeyCode) -> Option<InputAction> {
+        InputAction::ALL
+            .into_iter()
+            .find(|&action| self.keys(action).contains(&key))
+    }
+
+    /// Bind `key` to `action`, a key already bound to an action is returned as a conflict
+    pub fn bind(&mut self, action: InputAction, key: KeyCode) -> Result<(), InputAction> {
+        match self.action(key) {
+            Some(bound_action) => Err(bound_action),
+            None => {
+                self.0.entry(action).or_default().push(key);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn remove_last(&mut self, action: InputAction) {
+        if let Some(keys) = self.0.get_mut(&action) {
+            keys.pop();
+        }
+    }
+
+    pub fn just_pressed(&self, action: InputAction, keyboard_input: &Input<KeyCode>) -> bool {
+        keyboard_input.any_just_pressed(self.keys(action).iter().copied())
+    }
+
+    fn any_just_pressed(&self, keyboard_input: &Input<KeyCode>) -> bool {
+        InputAction::ALL
+            .into_iter()
+            .any(|action| self.just_pressed(action, keyboard_input))
+    }
+
+    pub fn keys_to_string(&self, action: InputAction) -> String {
+        match self.keys(action) {
+            [] => "-".to_string(),
+            keys => keys
+                .iter()
+                .map(|key| format!("{:?}", key))
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+}
+
+const GAMEPAD_STICK_THRESHOLD: f32 = 0.5;
+const GAMEPAD_REPEAT_DELAY: f32 = 0.3;
+const GAMEPAD_REPEAT_INTERVAL: f32 = 0.15;
 
 #[derive(Component)]
 pub struct InputBuffer {
     buffer: VecDeque<GameInput>,
     last_input: Option<GameInput>,
+    max_len: usize,
 }
 
-#[derive(Component)]
-pub struct InputInversionFlag(pub bool);
-#[derive(Component)]
-pub struct MoveImmediate(pub bool);
 #[derive(Resource, Default)]
 pub struct InputTimer(Timer);
 
+/// Pointer gesture in progress: a short tap moves the clicked block, a longer drag swipes
+#[derive(Default)]
+struct PointerGesture {
+    start: Option<Vec2>,
+    clicked: Option<Entity>,
+    swipe: Option<(i32, i32, usize)>, // direction and moves left to enqueue
+}
+
+/// Held gamepad direction, repeated after a delay
+#[derive(Default)]
+struct GamepadRepeat {
+    direction: Option<(i32, i32)>,
+    timer: Timer,
+}
+
 impl InputBuffer {
-    fn new() -> Self {
+    fn new(max_len: usize) -> Self {
         Self {
             buffer: VecDeque::new(),
             last_input: None,
+            max_len,
         }
     }
 
@@ -82,6 +222,10 @@ impl InputBuffer {
         self.buffer.pop_front()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
     fn try_push(
         &mut self,
         value: GameInput,
@@ -90,7 +234,8 @@ impl InputBuffer {
     ) -> Result<(), GameError> {
         if let Some(last_input) = self.last_input {
             if last_input == value {
-                if input_timer.0.finished() {
+                // a zero duration turns the repeat filter off
+                if input_timer.0.finished() || input_timer.0.duration().is_zero() {
                     self.push(value);
                     playlog.add_log(value);
                     input_timer.0.reset();
@@ -111,33 +256,28 @@ impl InputBuffer {
     }
 }
 
-pub fn setup_input(mut commands: Commands, mut input_timer: ResMut<InputTimer>) {
+pub fn setup_input(
+    mut commands: Commands,
+    mut input_timer: ResMut<InputTimer>,
+    settings: Res<Settings>,
+) {
     commands.spawn((
         Name::new("InputSystem"),
-        InputBuffer::new(),
-        InputInversionFlag(LocalStorage::get_input_inversion().unwrap_or(false)),
-        MoveImmediate(LocalStorage::get_move_immediate().unwrap_or(false)),
+        InputBuffer::new(settings.input_buffer_size),
     ));
-    *input_timer = InputTimer(Timer::from_seconds(0.03, TimerMode::Once));
-}
-
-fn just_pressed(keyboard_input: &Res<Input<KeyCode>>) -> bool {
-    keyboard_input.just_pressed(KeyCode::Up)
-        || keyboard_input.just_pressed(KeyCode::Down)
-        || keyboard_input.just_pressed(KeyCode::Left)
-        || keyboard_input.just_pressed(KeyCode::Right)
-        || keyboard_input.just_pressed(KeyCode::Z)
-        || keyboard_input.just_pressed(KeyCode::X)
+    *input_timer = InputTimer(Timer::from_seconds(settings.repeat_filter, TimerMode::Once));
 }
 
 fn input_keyboard(
     keyboard_input: Res<Input<KeyCode>>,
-    mut input_system: Query<(&mut InputBuffer, &InputInversionFlag)>,
+    mut input_buffer: Query<&mut InputBuffer>,
+    settings: Res<Settings>,
     mut input_timer: ResMut<InputTimer>,
     mut play_log: Query<&mut PlayLog>,
     time: Res<Time>,
     player_state: Res<State<PlayerState>>,
     game_mode: Res<State<GameMode>>,
+    camera_orbit: Res<CameraOrbit>,
 ) {
     if *player_state.current() != PlayerState::Idle
         && *player_state.current() != PlayerState::Shuffled
@@ -146,63 +286,58 @@ fn input_keyboard(
     {
         return;
     }
-    let (mut input_buffer, inversion_flag) = input_system.single_mut();
+    let mut input_buffer = input_buffer.single_mut();
     let mut play_log = play_log.single_mut();
-    if just_pressed(&keyboard_input) {
-        if keyboard_input.just_pressed(KeyCode::Up) {
+    let key_bindings = &settings.key_bindings;
+    if key_bindings.any_just_pressed(&keyboard_input) {
+        if key_bindings.just_pressed(InputAction::Up, &keyboard_input) {
+            let (dx, dz) = camera_orbit.screen_to_board(0, 1);
             InputHandler::direction(
-                0,
-                1,
-                inversion_flag.0,
+                dx,
+                dz,
+                settings.input_inversion,
                 &mut input_buffer,
                 &mut play_log,
                 &mut input_timer,
             );
-        } else if keyboard_input.just_pressed(KeyCode::Down) {
+        } else if key_bindings.just_pressed(InputAction::Down, &keyboard_input) {
+            let (dx, dz) = camera_orbit.screen_to_board(0, -1);
             InputHandler::direction(
-                0,
-                -1,
-                inversion_flag.0,
+                dx,
+                dz,
+                settings.input_inversion,
                 &mut input_buffer,
                 &mut play_log,
                 &mut input_timer,
             );
-        } else if keyboard_input.just_pressed(KeyCode::Left) {
+        } else if key_bindings.just_pressed(InputAction::Left, &keyboard_input) {
+            let (dx, dz) = camera_orbit.screen_to_board(1, 0);
             InputHandler::direction(
-                1,
-                0,
-                inversion_flag.0,
+                dx,
+                dz,
+                settings.input_inversion,
                 &mut input_buffer,
                 &mut play_log,
                 &mut input_timer,
             );
-        } else if keyboard_input.just_pressed(KeyCode::Right) {
+        } else if key_bindings.just_pressed(InputAction::Right, &keyboard_input) {
+            let (dx, dz) = camera_orbit.screen_to_board(-1, 0);
             InputHandler::direction(
-                -1,
-                0,
-                inversion_flag.0,
+                dx,
+                dz,
+                settings.input_inversion,
                 &mut input_buffer,
                 &mut play_log,
                 &mut input_timer,
             );
-        } else if keyboard_input.just_pressed(KeyCode::Z) {
+        } else if key_bindings.just_pressed(InputAction::Undo, &keyboard_input) {
             if *game_mode.current() == GameMode::Practice {
-                InputHandler::undo(
-                    inversion_flag.0,
-                    &mut input_buffer,
-                    &mut play_log,
-                    &mut input_timer,
-                )
+                InputHandler::undo(&mut input_buffer, &mut play_log, &mut input_timer)
             }
-        } else if keyboard_input.just_pressed(KeyCode::X)
+        } else if key_bindings.just_pressed(InputAction::Redo, &keyboard_input)
             && *game_mode.current() == GameMode::Practice
         {
-            InputHandler::redo(
-                inversion_flag.0,
-                &mut input_buffer,
-                &mut play_log,
-                &mut input_timer,
-            )
+            InputHandler::redo(&mut input_buffer, &mut play_log, &mut input_timer)
         }
     }
     input_timer.0.tick(time.delta());
@@ -212,34 +347,268 @@ fn input_click(
     mut game_query: Query<&GameState>,
     transforms: Query<&mut Transform>,
     mut input_timer: ResMut<InputTimer>,
-    mut input_system: Query<(&mut InputBuffer, &InputInversionFlag)>,
+    mut input_buffer: Query<&mut InputBuffer>,
     mut play_log: Query<&mut PlayLog>,
     mut events: EventReader<PickingEvent>,
     player_state: Res<State<PlayerState>>,
+    mouse_input: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    windows: Res<Windows>,
+    settings: Res<Settings>,
+    camera_orbit: Res<CameraOrbit>,
+    mut gesture: Local<PointerGesture>,
 ) {
     if *player_state.current() != PlayerState::Idle
         && *player_state.current() != PlayerState::Shuffled
         && *player_state.current() != PlayerState::Solving
         && *player_state.current() != PlayerState::Clear
     {
+        *gesture = PointerGesture::default();
         return;
     }
     let game = game_query.single_mut();
+    let mut input_buffer = input_buffer.single_mut();
     let mut play_log = play_log.single_mut();
     for event in events.iter() {
         if let PickingEvent::Clicked(e) = event {
-            let transform = transforms.get(*e).unwrap();
+            gesture.clicked = Some(*e);
+        }
+    }
+
+    // pointer positions with the origin at the bottom left like the cursor, touches are top left
+    let window = windows.get_primary();
+    let cursor_position = || window.and_then(|window| window.cursor_position());
+    let touch_position = |touch: &Touch| {
+        let height = window.map_or(0.0, |window| window.height());
+        Vec2::new(touch.position().x, height - touch.position().y)
+    };
+    let pressed = touches
+        .iter_just_pressed()
+        .next()
+        .map(touch_position)
+        .or_else(|| match mouse_input.just_pressed(MouseButton::Left) {
+            true => cursor_position(),
+            false => None,
+        });
+    let released = touches
+        .iter_just_released()
+        .next()
+        .map(touch_position)
+        .or_else(|| match mouse_input.just_released(MouseButton::Left) {
+            true => cursor_position(),
+            false => None,
+        });
+
+    // two fingers orbit and zoom the camera instead
+    if touches.iter().nth(1).is_some() {
+        *gesture = PointerGesture::default();
+        return;
+    }
+
+    if let Some(position) = pressed {
+        gesture.start = Some(position);
+        gesture.swipe = None;
+    }
+    if let (Some(start), Some(end)) = (gesture.start, released) {
+        let delta = end - start;
+        let distance = delta.x.abs().max(delta.y.abs());
+        if distance >= settings.swipe_threshold {
+            // swipe: roll blocks toward the swipe direction, a cell per threshold distance
+            let (dx, dz) = if delta.x.abs() > delta.y.abs() {
+                if delta.x > 0.0 {
+                    (-1, 0)
+                } else {
+                    (1, 0)
+                }
+            } else if delta.y > 0.0 {
+                (0, 1)
+            } else {
+                (0, -1)
+            };
+            let (dx, dz) = camera_orbit.screen_to_board(dx, dz);
+            let movable = match (dx, dz) {
+                (1, 0) => game.size as i32 - 1 - game.x,
+                (-1, 0) => game.x,
+                (0, 1) => game.size as i32 - 1 - game.z,
+                _ => game.z,
+            };
+            let moves = ((distance / settings.swipe_threshold) as usize).min(movable as usize);
+            if moves > 0 {
+                gesture.swipe = Some((dx, dz, moves));
+            }
+        } else if let Some(e) = gesture.clicked {
+            // tap: move the clicked block
+            let transform = transforms.get(e).unwrap();
             let dx = transform.translation.x.round() as i32 - game.x;
             let dz = transform.translation.z.round() as i32 - game.z;
             InputHandler::direction(
                 dx,
                 dz,
                 false,
-                &mut input_system.single_mut().0,
+                &mut input_buffer,
                 &mut play_log,
                 &mut input_timer,
             );
         }
+        gesture.start = None;
+        gesture.clicked = None;
+    }
+
+    // a multi-cell swipe is enqueued a move at a time, as the buffer and input timer allow
+    if let Some((dx, dz, moves)) = gesture.swipe {
+        if InputHandler::direction(
+            dx,
+            dz,
+            false,
+            &mut input_buffer,
+            &mut play_log,
+            &mut input_timer,
+        ) {
+            gesture.swipe = match moves > 1 {
+                true => Some((dx, dz, moves - 1)),
+                false => None,
+            };
+        }
+    }
+}
+
+/// A smaller buffer takes effect as the queue drains, queued inputs are already logged
+fn apply_input_settings(
+    mut settings_changed_event: EventReader<SettingsChangedEvent>,
+    settings: Res<Settings>,
+    mut input_buffer: Query<&mut InputBuffer>,
+    mut input_timer: ResMut<InputTimer>,
+) {
+    if settings_changed_event.iter().last().is_none() {
+        return;
+    }
+    let mut input_buffer = input_buffer.single_mut();
+    input_buffer.max_len = settings.input_buffer_size;
+    input_timer
+        .0
+        .set_duration(Duration::from_secs_f32(settings.repeat_filter));
+}
+
+/// Direction of the D-pad, or of the left stick past the threshold
+fn gamepad_direction(
+    gamepad: Gamepad,
+    button_input: &Input<GamepadButton>,
+    axes: &Axis<GamepadAxis>,
+) -> Option<(i32, i32)> {
+    let pressed = |button_type| button_input.pressed(GamepadButton::new(gamepad, button_type));
+    if pressed(GamepadButtonType::DPadUp) {
+        return Some((0, 1));
+    } else if pressed(GamepadButtonType::DPadDown) {
+        return Some((0, -1));
+    } else if pressed(GamepadButtonType::DPadLeft) {
+        return Some((1, 0));
+    } else if pressed(GamepadButtonType::DPadRight) {
+        return Some((-1, 0));
+    }
+
+    let axis = |axis_type| {
+        axes.get(GamepadAxis::new(gamepad, axis_type))
+            .unwrap_or(0.0)
+    };
+    let x = axis(GamepadAxisType::LeftStickX);
+    let y = axis(GamepadAxisType::LeftStickY);
+    if x.abs().max(y.abs()) < GAMEPAD_STICK_THRESHOLD {
+        None
+    } else if x.abs() > y.abs() {
+        Some(if x > 0.0 { (-1, 0) } else { (1, 0) })
+    } else {
+        Some(if y > 0.0 { (0, 1) } else { (0, -1) })
+    }
+}
+
+fn input_gamepad(
+    gamepads: Res<Gamepads>,
+    button_input: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    settings: Res<Settings>,
+    mut repeat: Local<GamepadRepeat>,
+    time: Res<Time>,
+    mut input_buffer: Query<&mut InputBuffer>,
+    mut input_timer: ResMut<InputTimer>,
+    mut play_log: Query<&mut PlayLog>,
+    mut game_query: Query<&mut GameState>,
+    mut transforms: Query<&mut Transform>,
+    mut move_timer: ResMut<MoveTimer>,
+    mut player_state: ResMut<State<PlayerState>>,
+    game_mode: Res<State<GameMode>>,
+    camera_orbit: Res<CameraOrbit>,
+    (player_identity, network_channel): (Res<PlayerIdentity>, Res<NetworkChannel>),
+) {
+    if *player_state.current() != PlayerState::Idle
+        && *player_state.current() != PlayerState::Shuffled
+        && *player_state.current() != PlayerState::Solving
+        && *player_state.current() != PlayerState::Clear
+    {
+        repeat.direction = None;
+        return;
+    }
+    let mut input_buffer = input_buffer.single_mut();
+    let mut play_log = play_log.single_mut();
+
+    // direction: moves on press, then repeats while held
+    let direction = gamepads
+        .iter()
+        .find_map(|gamepad| gamepad_direction(gamepad, &button_input, &axes));
+    let mut move_direction = None;
+    if direction != repeat.direction {
+        repeat.direction = direction;
+        repeat.timer = Timer::from_seconds(GAMEPAD_REPEAT_DELAY, TimerMode::Once);
+        move_direction = direction;
+    } else if direction.is_some() && repeat.timer.tick(time.delta()).just_finished() {
+        repeat.timer = Timer::from_seconds(GAMEPAD_REPEAT_INTERVAL, TimerMode::Once);
+        move_direction = direction;
+    }
+    if let Some((dx, dz)) = move_direction {
+        let (dx, dz) = camera_orbit.screen_to_board(dx, dz);
+        InputHandler::direction(
+            dx,
+            dz,
+            settings.input_inversion,
+            &mut input_buffer,
+            &mut play_log,
+            &mut input_timer,
+        );
+    }
+
+    // buttons: bumpers undo and redo, start shuffles and select resets
+    let just_pressed = |button_type| {
+        gamepads
+            .iter()
+            .any(|gamepad| button_input.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+    if *game_mode.current() == GameMode::Practice {
+        if just_pressed(GamepadButtonType::LeftTrigger) {
+            InputHandler::undo(&mut input_buffer, &mut play_log, &mut input_timer);
+        } else if just_pressed(GamepadButtonType::RightTrigger) {
+            InputHandler::redo(&mut input_buffer, &mut play_log, &mut input_timer);
+        }
+    }
+    if *game_mode.current() != GameMode::DailyPuzzle {
+        let mut game = game_query.single_mut();
+        if just_pressed(GamepadButtonType::Start) {
+            shuffle(
+                &mut game,
+                &mut transforms,
+                &mut play_log,
+                game_mode.current(),
+                &settings,
+                &player_identity,
+                &mut player_state,
+                &network_channel,
+            );
+        } else if just_pressed(GamepadButtonType::Select) {
+            game.reset(&mut move_timer, &mut transforms);
+            play_log.reset();
+            game.is_shuffled = false;
+            if *player_state.current() != PlayerState::Idle {
+                let _ = player_state.set(PlayerState::Idle);
+            }
+        }
     }
 }
 
@@ -253,14 +622,16 @@ impl InputHandler {
         input_buffer: &mut InputBuffer,
         play_log: &mut PlayLog,
         input_timer: &mut ResMut<InputTimer>,
-    ) {
-        if enqueue_input(dx, dz, inverse, input_buffer, play_log, input_timer).is_ok() {
+    ) -> bool {
+        let enqueued = enqueue_input(dx, dz, inverse, input_buffer, play_log, input_timer).is_ok();
+        if enqueued {
             play_log.clear_redo_buf();
         }
+        enqueued
     }
 
+    /// Logged inputs are the moves made, so undo and redo don't depend on input inversion
     pub fn undo(
-        inverse: bool,
         input_buffer: &mut InputBuffer,
         play_log: &mut PlayLog,
         input_timer: &mut ResMut<InputTimer>,
@@ -270,7 +641,7 @@ impl InputHandler {
             match enqueue_input(
                 input.dx(),
                 input.dy(),
-                !inverse,
+                true,
                 input_buffer,
                 play_log,
                 input_timer,
@@ -286,7 +657,6 @@ impl InputHandler {
     }
 
     pub fn redo(
-        inverse: bool,
         input_buffer: &mut InputBuffer,
         play_log: &mut PlayLog,
         input_timer: &mut ResMut<InputTimer>,
@@ -296,7 +666,7 @@ impl InputHandler {
             match enqueue_input(
                 input.dx(),
                 input.dy(),
-                !inverse,
+                true,
                 input_buffer,
                 play_log,
                 input_timer,
@@ -316,7 +686,7 @@ fn enqueue_input(
     play_log: &mut PlayLog,
     input_timer: &mut ResMut<InputTimer>,
 ) -> Result<(), GameError> {
-    if input_buffer.buffer.len() < BUFFER_MAX {
+    if input_buffer.buffer.len() < input_buffer.max_len {
         match (dx, dz) {
             (0, 1) => {
                 if inverse {
@@ -0,0 +1,235 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, SQRT_2};
+
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    math::vec3,
+    prelude::*,
+    render::camera::ScalingMode,
+};
+use bevy_mod_picking::PickingCameraBundle;
+
+use crate::{game::GameState, player::PlayerState, settings::Settings};
+
+const ORBIT_SPEED: f32 = 0.01; // radians per logical pixel
+const ZOOM_SPEED: f32 = 0.1; // zoom change per scroll line
+const PIXELS_PER_LINE: f32 = 100.0;
+const MIN_PITCH: f32 = 0.1;
+const MAX_PITCH: f32 = FRAC_PI_2;
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 2.0;
+const FRAMING_DISTANCE: f32 = 1.25 * SQRT_2; // camera distance per board cell at zoom 1
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CameraOrbit::new(CameraPreset::Default))
+            .add_startup_system(setup_camera)
+            .add_system(camera_control)
+            .add_system(update_camera.after(camera_control));
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CameraPreset {
+    Default,
+    TopDown,
+    Side,
+    /// orthographic top-down view with face indicators
+    Flat,
+}
+
+impl CameraPreset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraPreset::Default => "Default",
+            CameraPreset::TopDown => "Top",
+            CameraPreset::Side => "Side",
+            CameraPreset::Flat => "2D",
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            CameraPreset::Default => CameraPreset::TopDown,
+            CameraPreset::TopDown => CameraPreset::Side,
+            CameraPreset::Side => CameraPreset::Flat,
+            CameraPreset::Flat => CameraPreset::Default,
+        }
+    }
+
+    /// yaw and pitch of the preset
+    fn angles(&self) -> (f32, f32) {
+        match self {
+            CameraPreset::Default => (0.0, FRAC_PI_4),
+            CameraPreset::TopDown => (0.0, FRAC_PI_2),
+            CameraPreset::Side => (FRAC_PI_2, PI / 12.0),
+            CameraPreset::Flat => (0.0, FRAC_PI_2),
+        }
+    }
+}
+
+/// Camera orbit around the board center, framed by the board size
+#[derive(Resource, Debug)]
+pub struct CameraOrbit {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub zoom: f32,
+    /// `None` after the camera is moved by hand
+    pub preset: Option<CameraPreset>,
+    /// looks straight down without perspective, can't be orbited
+    pub orthographic: bool,
+}
+
+impl CameraOrbit {
+    fn new(preset: CameraPreset) -> Self {
+        let (yaw, pitch) = preset.angles();
+        Self {
+            yaw,
+            pitch,
+            zoom: 1.0,
+            preset: Some(preset),
+            orthographic: preset == CameraPreset::Flat,
+        }
+    }
+
+    pub fn next_preset(&mut self) {
+        *self = Self::new(
+            self.preset
+                .map_or(CameraPreset::Default, |preset| preset.next()),
+        );
+    }
+
+    fn orbit(&mut self, delta: Vec2) {
+        if self.orthographic {
+            return;
+        }
+        self.yaw = (self.yaw - delta.x * ORBIT_SPEED).rem_euclid(2.0 * PI);
+        self.pitch = (self.pitch + delta.y * ORBIT_SPEED).clamp(MIN_PITCH, MAX_PITCH);
+        self.preset = None;
+    }
+
+    fn zoom(&mut self, scale: f32) {
+        self.zoom = (self.zoom * scale).clamp(MIN_ZOOM, MAX_ZOOM);
+        if !self.orthographic {
+            self.preset = None;
+        }
+    }
+
+    fn transform(&self, board_size: usize) -> Transform {
+        let center = (board_size as f32 - 1.0) / 2.0;
+        let rotation = Quat::from_euler(EulerRot::YXZ, self.yaw, -self.pitch, 0.0);
+        let distance = board_size as f32 * FRAMING_DISTANCE * self.zoom;
+        Transform {
+            translation: vec3(center, 0.0, center) + rotation * Vec3::Z * distance,
+            rotation,
+            ..default()
+        }
+    }
+
+    fn projection(&self, board_size: usize) -> Projection {
+        match self.orthographic {
+            true => {
+                // the board with half a cell of margin on each side
+                let extent = (board_size as f32 + 1.0) * self.zoom;
+                Projection::Orthographic(OrthographicProjection {
+                    scaling_mode: ScalingMode::Auto {
+                        min_width: extent,
+                        min_height: extent,
+                    },
+                    ..default()
+                })
+            }
+            false => Projection::Perspective(default()),
+        }
+    }
+
+    /// Rotates an input direction given for the default view to the nearest board axis of the view,
+    /// so that "up" keeps moving away from the camera
+    pub fn screen_to_board(&self, dx: i32, dz: i32) -> (i32, i32) {
+        let quarter_turns = (self.yaw / FRAC_PI_2).round() as i32;
+        (0..quarter_turns.rem_euclid(4)).fold((dx, dz), |(x, z), _| (z, -x))
+    }
+}
+
+fn setup_camera(mut commands: Commands, camera_orbit: Res<CameraOrbit>, settings: Res<Settings>) {
+    commands.spawn((
+        Camera3dBundle {
+            transform: camera_orbit.transform(settings.board_size),
+            projection: camera_orbit.projection(settings.board_size),
+            ..default()
+        },
+        PickingCameraBundle::default(),
+    ));
+}
+
+/// Orbit by dragging with the right mouse button or two fingers, zoom by wheel or pinch
+fn camera_control(
+    mut camera_orbit: ResMut<CameraOrbit>,
+    mut mouse_motion_events: EventReader<MouseMotion>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    mouse_input: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    player_state: Res<State<PlayerState>>,
+) {
+    if *player_state.current() != PlayerState::Idle
+        && *player_state.current() != PlayerState::Shuffled
+        && *player_state.current() != PlayerState::Solving
+        && *player_state.current() != PlayerState::Clear
+    {
+        mouse_motion_events.clear();
+        mouse_wheel_events.clear();
+        return;
+    }
+
+    if mouse_input.pressed(MouseButton::Right) {
+        let delta: Vec2 = mouse_motion_events.iter().map(|event| event.delta).sum();
+        if delta != Vec2::ZERO {
+            // mouse motion grows downward
+            camera_orbit.orbit(Vec2::new(delta.x, -delta.y));
+        }
+    } else {
+        mouse_motion_events.clear();
+    }
+
+    for event in mouse_wheel_events.iter() {
+        let lines = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        };
+        camera_orbit.zoom(1.0 - lines * ZOOM_SPEED);
+    }
+
+    // touch positions grow downward
+    let mut fingers = touches.iter();
+    if let (Some(first), Some(second), None) = (fingers.next(), fingers.next(), fingers.next()) {
+        let previous_distance = first
+            .previous_position()
+            .distance(second.previous_position());
+        let distance = first.position().distance(second.position());
+        if distance > 0.0 && previous_distance != distance {
+            camera_orbit.zoom(previous_distance / distance);
+        }
+        let delta = (first.delta() + second.delta()) / 2.0;
+        if delta != Vec2::ZERO {
+            camera_orbit.orbit(Vec2::new(delta.x, -delta.y));
+        }
+    }
+}
+
+/// Frames the board from the orbit, also when the board is rebuilt with another size
+fn update_camera(
+    camera_orbit: Res<CameraOrbit>,
+    game_query: Query<&GameState>,
+    mut camera_query: Query<(&mut Transform, &mut Projection), With<Camera>>,
+    mut framed_size: Local<usize>,
+) {
+    let board_size = game_query.single().size;
+    if !camera_orbit.is_changed() && *framed_size == board_size {
+        return;
+    }
+    *framed_size = board_size;
+    let (mut transform, mut projection) = camera_query.single_mut();
+    *transform = camera_orbit.transform(board_size);
+    *projection = camera_orbit.projection(board_size);
+}
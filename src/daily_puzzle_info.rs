@@ -1,18 +1,87 @@
+use std::time::Duration;
+
 use bevy::{prelude::*, utils::HashMap};
-use chrono::{Datelike, NaiveDate};
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     game::GameState,
     local_storage::LocalStorage,
     network::{BoardString, Network, NetworkChannel},
-    player::PlayerState,
+    player::{PlayLog, PlayerInfo, PlayerState},
     ui::GameMode,
     utils::string_to_board,
 };
 
+/// Play record of a daily puzzle on this device
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct DailyRecord {
+    pub cleared: bool,
+    pub best_time: Option<Duration>,
+    pub best_moves: Option<usize>,
+    pub attempts: usize, // 0 for histories before records were kept
+    pub undo_used: bool,
+    pub first_clear: Option<NaiveDateTime>, // None for histories before records were kept
+}
+
 #[derive(Serialize, Deserialize, Default, Debug)]
-pub struct ClearHistory(HashMap<i32, HashMap<u32, Vec<bool>>>);
+#[serde(from = "ClearHistoryFormat")]
+pub struct ClearHistory(HashMap<NaiveDate, DailyRecord>);
+
+/// Stored formats of `ClearHistory`, old formats are migrated on load
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ClearHistoryFormat {
+    Records(HashMap<NaiveDate, DailyRecord>),
+    // year -> month -> cleared of each day, untagged enums can't parse numeric map keys
+    Calendar(HashMap<String, HashMap<String, Vec<bool>>>),
+}
+
+impl From<ClearHistoryFormat> for ClearHistory {
+    fn from(format: ClearHistoryFormat) -> Self {
+        match format {
+            ClearHistoryFormat::Records(records) => Self(records),
+            ClearHistoryFormat::Calendar(calendar) => {
+                let mut records = HashMap::new();
+                for (year, year_history) in calendar {
+                    for (month, month_history) in year_history {
+                        let (year, month) = match (year.parse(), month.parse()) {
+                            (Ok(year), Ok(month)) => (year, month),
+                            _ => continue,
+                        };
+                        for (i, _) in month_history.iter().enumerate().filter(|(_, &clear)| clear) {
+                            if let Some(date) = NaiveDate::from_ymd_opt(year, month, i as u32 + 1) {
+                                records.insert(
+                                    date,
+                                    DailyRecord {
+                                        cleared: true,
+                                        ..default()
+                                    },
+                                );
+                            }
+                        }
+                    }
+                }
+                Self(records)
+            }
+        }
+    }
+}
+
+/// Daily puzzles fetched so far, kept in local storage to play them offline
+#[derive(Serialize, Deserialize, Default)]
+pub struct DailyPuzzleCache {
+    pub first_date: NaiveDate,
+    pub last_date: NaiveDate,
+    pub daily_puzzles: HashMap<NaiveDate, BoardString>,
+}
+
+/// Summary of the daily puzzles of a month
+pub struct MonthStatistics {
+    pub cleared: usize,
+    pub total: usize,
+    pub average_time: Option<Duration>,
+}
 
 #[derive(Component, Default, Debug)]
 pub struct DailyPuzzleInfo {
@@ -20,61 +89,161 @@ pub struct DailyPuzzleInfo {
     pub last_date: NaiveDate,
     pub current_date: NaiveDate,
     daily_puzzles: HashMap<NaiveDate, BoardString>,
+    // generated while offline, not cached so the server is asked again on the next launch
+    generated_puzzles: HashMap<NaiveDate, BoardString>,
     pub clear_history: ClearHistory,
 }
 
 impl ClearHistory {
     pub fn get(&self, date: NaiveDate) -> bool {
-        let year = date.year_ce().1 as i32;
-        let month = date.month();
-        let day = date.day();
-        if let Some(year_history) = self.0.get(&year) {
-            if let Some(month_history) = year_history.get(&month) {
-                month_history[day as usize - 1]
-            } else {
-                false
-            }
-        } else {
-            false
+        self.0.get(&date).map_or(false, |record| record.cleared)
+    }
+
+    pub fn record(&self, date: NaiveDate) -> Option<&DailyRecord> {
+        self.0.get(&date)
+    }
+
+    pub fn add_attempt(&mut self, date: NaiveDate) {
+        self.0.entry(date).or_default().attempts += 1;
+    }
+
+    pub fn add_clear(
+        &mut self,
+        date: NaiveDate,
+        time: Duration,
+        moves: usize,
+        undo_used: bool,
+        now: NaiveDateTime,
+    ) {
+        let record = self.0.entry(date).or_default();
+        if !record.cleared {
+            record.cleared = true;
+            record.first_clear = Some(now);
         }
+        record.best_time = Some(record.best_time.map_or(time, |best| best.min(time)));
+        record.best_moves = Some(record.best_moves.map_or(moves, |best| best.min(moves)));
+        record.undo_used |= undo_used;
     }
 
-    pub fn set(&mut self, date: NaiveDate, val: bool) {
-        let year = date.year_ce().1 as i32;
-        let month = date.month();
-        let day = date.day();
-        if !self.0.contains_key(&year) {
-            self.0.insert(year, HashMap::new());
+    /// Consecutive cleared days up to `today`, today counts once it is cleared
+    pub fn current_streak(&self, today: NaiveDate) -> usize {
+        let start = if self.get(today) {
+            Some(today)
+        } else {
+            today.pred_opt()
+        };
+        start.map_or(0, |start| {
+            start
+                .iter_days()
+                .rev()
+                .take_while(|&date| self.get(date))
+                .count()
+        })
+    }
+
+    pub fn longest_streak(&self) -> usize {
+        let mut dates: Vec<_> = self
+            .0
+            .iter()
+            .filter(|(_, record)| record.cleared)
+            .map(|(&date, _)| date)
+            .collect();
+        dates.sort();
+
+        let mut longest = 0;
+        let mut streak = 0;
+        let mut prev: Option<NaiveDate> = None;
+        for date in dates {
+            streak = match prev {
+                Some(prev) if prev.succ_opt() == Some(date) => streak + 1,
+                _ => 1,
+            };
+            longest = longest.max(streak);
+            prev = Some(date);
         }
-        let year_history = self.0.get_mut(&year).unwrap();
-        let days_in_month = |y: i32, m: u32| {
-            NaiveDate::from_ymd_opt(
-                match m {
-                    12 => y + 1,
-                    _ => y,
-                },
-                match m {
-                    12 => 1,
-                    _ => m + 1,
-                },
-                1,
-            )
+        longest
+    }
+
+    /// Statistics of the month of `month`, counting only dates in `first_date..=last_date`
+    pub fn month_statistics(
+        &self,
+        month: NaiveDate,
+        first_date: NaiveDate,
+        last_date: NaiveDate,
+    ) -> MonthStatistics {
+        let dates = month
+            .with_day(1)
             .unwrap()
-            .signed_duration_since(NaiveDate::from_ymd_opt(y, m, 1).unwrap())
-            .num_days()
+            .iter_days()
+            .take_while(|date| date.month() == month.month())
+            .filter(|&date| first_date <= date && date <= last_date);
+
+        let mut statistics = MonthStatistics {
+            cleared: 0,
+            total: 0,
+            average_time: None,
         };
-        if !year_history.contains_key(&month) {
-            year_history.insert(month, vec![false; days_in_month(year, month) as usize]);
+        let mut times = Vec::new();
+        for date in dates {
+            statistics.total += 1;
+            if let Some(record) = self.record(date).filter(|record| record.cleared) {
+                statistics.cleared += 1;
+                times.extend(record.best_time);
+            }
+        }
+        if !times.is_empty() {
+            statistics.average_time = Some(times.iter().sum::<Duration>() / times.len() as u32);
         }
-        let month_history = year_history.get_mut(&month).unwrap();
-        month_history[day as usize - 1] = val;
+        statistics
     }
 }
 
 impl DailyPuzzleInfo {
+    /// A prefetched puzzle may arrive again by a direct request, so overwriting is allowed
     pub fn insert_daily_puzzle(&mut self, date: NaiveDate, board_string: BoardString) {
-        assert!(!self.daily_puzzles.contains_key(&date));
         self.daily_puzzles.insert(date, board_string);
+        self.generated_puzzles.remove(&date);
+        self.save_cache();
+    }
+
+    pub fn set_dates(&mut self, first_date: NaiveDate, last_date: NaiveDate) {
+        self.first_date = first_date;
+        self.last_date = last_date;
+        self.current_date = last_date;
+        self.save_cache();
+    }
+
+    /// Dates of the local day while offline without a cache, not saved
+    pub fn set_local_dates(&mut self, today: NaiveDate) {
+        self.first_date = today;
+        self.last_date = today;
+        self.current_date = today;
+    }
+
+    /// Generate the puzzle of `date` locally when the server can't be reached
+    pub fn insert_generated_daily_puzzle(&mut self, date: NaiveDate) {
+        if !self.daily_puzzles.contains_key(&date) {
+            self.generated_puzzles
+                .insert(date, BoardString::daily_puzzle(date));
+        }
+    }
+
+    /// Generated puzzles differ from the server's, so their scores can't be enrolled
+    pub fn is_generated(&self, date: NaiveDate) -> bool {
+        self.generated_puzzles.contains_key(&date)
+    }
+
+    /// Whether first and last dates are known, from the server or the local storage
+    pub fn has_cache(&self) -> bool {
+        self.last_date != NaiveDate::default()
+    }
+
+    fn save_cache(&self) {
+        LocalStorage::set_daily_puzzle_cache(&DailyPuzzleCache {
+            first_date: self.first_date,
+            last_date: self.last_date,
+            daily_puzzles: self.daily_puzzles.clone(),
+        });
     }
 
     pub fn load_daily_puzzle(
@@ -85,12 +254,26 @@ impl DailyPuzzleInfo {
         player_state: &mut ResMut<State<PlayerState>>,
         network_channel: &Res<NetworkChannel>,
     ) -> bool {
-        if let Some(board_string) = self.daily_puzzles.get(&date) {
+        let board_string = self
+            .daily_puzzles
+            .get(&date)
+            .or_else(|| self.generated_puzzles.get(&date));
+        if let Some(board_string) = board_string {
             string_to_board(board_string, transforms, game);
             if *player_state.current() != PlayerState::Shuffled {
                 // inactive stack에 있는 것이 무엇이든 Shuffled로 바꾸기 위해 replace 사용
                 player_state.replace(PlayerState::Shuffled).unwrap();
             }
+
+            // prefetch adjacent dates so they can be played offline
+            for adjacent_date in [date.pred_opt(), date.succ_opt()].into_iter().flatten() {
+                if self.first_date <= adjacent_date
+                    && adjacent_date <= self.last_date
+                    && !self.daily_puzzles.contains_key(&adjacent_date)
+                {
+                    Network::prefetch_daily_puzzle(adjacent_date, network_channel);
+                }
+            }
             true
         } else {
             Network::get_daily_puzzle(date, player_state, network_channel);
@@ -104,6 +287,9 @@ pub struct DailyPuzzleInfoPlugin;
 impl Plugin for DailyPuzzleInfoPlugin {
     fn build(&self, app: &mut App) {
         app.add_startup_system(init_daily_puzzle_info)
+            .add_system_set(
+                SystemSet::on_enter(PlayerState::Solving).with_system(on_start_daily_puzzle),
+            )
             .add_system_set(
                 SystemSet::on_enter(PlayerState::Clear).with_system(on_clear_daily_puzzle),
             );
@@ -118,20 +304,108 @@ fn init_daily_puzzle_info(mut commands: Commands) {
         daily_puzzle_info.clear_history = clear_history;
     }
 
+    // fetched daily puzzles
+    if let Some(cache) = LocalStorage::get_daily_puzzle_cache() {
+        daily_puzzle_info.first_date = cache.first_date;
+        daily_puzzle_info.last_date = cache.last_date;
+        daily_puzzle_info.current_date = cache.last_date;
+        daily_puzzle_info.daily_puzzles = cache.daily_puzzles;
+    }
+
     commands.spawn(daily_puzzle_info);
 }
 
+fn on_start_daily_puzzle(
+    game_mode: Res<State<GameMode>>,
+    mut daily_puzzle_info_query: Query<&mut DailyPuzzleInfo>,
+) {
+    if *game_mode.current() != GameMode::DailyPuzzle {
+        return;
+    }
+    let mut daily_puzzle_info = daily_puzzle_info_query.single_mut();
+
+    let date = daily_puzzle_info.current_date;
+    daily_puzzle_info.clear_history.add_attempt(date);
+
+    LocalStorage::set_daily_puzzle_clear_history(&daily_puzzle_info.clear_history);
+}
+
 fn on_clear_daily_puzzle(
     game_mode: Res<State<GameMode>>,
     mut daily_puzzle_info_query: Query<&mut DailyPuzzleInfo>,
+    player_info_query: Query<&PlayerInfo>,
+    play_log_query: Query<&PlayLog>,
 ) {
     if *game_mode.current() != GameMode::DailyPuzzle {
         return;
     }
     let mut daily_puzzle_info = daily_puzzle_info_query.single_mut();
+    let (time, moves) = player_info_query.single().get_player_info();
 
     let date = daily_puzzle_info.current_date;
-    daily_puzzle_info.clear_history.set(date, true);
+    daily_puzzle_info.clear_history.add_clear(
+        date,
+        time,
+        moves,
+        play_log_query.single().undo_used,
+        Local::now().naive_local(),
+    );
 
     LocalStorage::set_daily_puzzle_clear_history(&daily_puzzle_info.clear_history);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 1, day).unwrap()
+    }
+
+    #[test]
+    fn calendar_history_is_migrated() {
+        let clear_history: ClearHistory =
+            serde_json::from_str(r#"{"2023": {"1": [true, false, true]}}"#).unwrap();
+        assert!(clear_history.get(date(1)));
+        assert!(!clear_history.get(date(2)));
+        assert!(clear_history.get(date(3)));
+
+        let record = clear_history.record(date(1)).unwrap();
+        assert_eq!(record.attempts, 0);
+        assert_eq!((record.best_time, record.best_moves), (None, None));
+        assert_eq!(record.first_clear, None);
+        assert_eq!(clear_history.longest_streak(), 1);
+    }
+
+    #[test]
+    fn record_history_round_trips() {
+        let mut clear_history = ClearHistory::default();
+        clear_history.add_attempt(date(1));
+        clear_history.add_clear(
+            date(1),
+            Duration::from_secs(30),
+            40,
+            false,
+            date(1).and_hms_opt(9, 0, 0).unwrap(),
+        );
+        clear_history.add_attempt(date(2));
+
+        let clear_history: ClearHistory =
+            serde_json::from_str(&serde_json::to_string(&clear_history).unwrap()).unwrap();
+        let record = clear_history.record(date(1)).unwrap();
+        assert!(record.cleared);
+        assert_eq!(record.attempts, 1);
+        assert_eq!(record.best_moves, Some(40));
+        assert!(!clear_history.get(date(2)));
+        assert_eq!(clear_history.record(date(2)).unwrap().attempts, 1);
+    }
+
+    #[test]
+    fn generated_puzzles_are_not_cached() {
+        let mut daily_puzzle_info = DailyPuzzleInfo::default();
+        daily_puzzle_info.set_local_dates(date(1));
+        daily_puzzle_info.insert_generated_daily_puzzle(date(1));
+        assert!(daily_puzzle_info.is_generated(date(1)));
+        assert!(daily_puzzle_info.daily_puzzles.is_empty());
+    }
+}
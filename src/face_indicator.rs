@@ -0,0 +1,128 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{math::vec3, prelude::*};
+
+use crate::{
+    block::{BlockMesh, FACE_UVS},
+    camera::CameraOrbit,
+};
+
+const INDICATOR_SIZE: f32 = 0.22;
+const INDICATOR_OFFSET: f32 = 0.36; // from the block center toward its side
+const INDICATOR_HEIGHT: f32 = 0.51; // just above the top face
+
+/// World directions of the sides shown by indicators
+const SIDES: [Vec3; 4] = [Vec3::X, Vec3::NEG_X, Vec3::Z, Vec3::NEG_Z];
+
+pub struct FaceIndicatorPlugin;
+
+impl Plugin for FaceIndicatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_face_meshes)
+            .add_system(spawn_face_indicators)
+            .add_system(update_face_indicators);
+    }
+}
+
+/// Flat quads textured with each cube face, in the order of `FACE_UVS`
+#[derive(Resource)]
+struct FaceMeshes(Vec<Handle<Mesh>>);
+
+/// Shows which face of `block` looks toward `side`, drawn on the top face in the orthographic view
+#[derive(Component)]
+struct FaceIndicator {
+    block: Entity,
+    side: Vec3,
+}
+
+fn setup_face_meshes(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    let face_meshes = FACE_UVS
+        .iter()
+        .map(|uvs| {
+            // quad vertices run bottom left, top left, top right, bottom right
+            let mut mesh = Mesh::from(shape::Quad::new(Vec2::splat(INDICATOR_SIZE)));
+            mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![uvs[0], uvs[3], uvs[2], uvs[1]]);
+            meshes.add(mesh)
+        })
+        .collect();
+    commands.insert_resource(FaceMeshes(face_meshes));
+}
+
+fn spawn_face_indicators(
+    mut commands: Commands,
+    block_query: Query<(Entity, &Handle<StandardMaterial>), Added<BlockMesh>>,
+    face_meshes: Res<FaceMeshes>,
+) {
+    for (block, material) in block_query.iter() {
+        for side in SIDES {
+            commands.spawn((
+                PbrBundle {
+                    mesh: face_meshes.0[0].clone(),
+                    material: material.clone(),
+                    visibility: Visibility::INVISIBLE,
+                    ..default()
+                },
+                FaceIndicator { block, side },
+            ));
+        }
+    }
+}
+
+/// Follows the blocks and shows the faces turned to each side, despawned with their block
+fn update_face_indicators(
+    mut commands: Commands,
+    mut indicator_query: Query<(
+        Entity,
+        &FaceIndicator,
+        &mut Transform,
+        &mut Handle<Mesh>,
+        &mut Visibility,
+    )>,
+    block_query: Query<&Transform, (With<BlockMesh>, Without<FaceIndicator>)>,
+    face_meshes: Res<FaceMeshes>,
+    camera_orbit: Res<CameraOrbit>,
+) {
+    for (entity, indicator, mut transform, mut mesh, mut visibility) in indicator_query.iter_mut() {
+        let block_transform = match block_query.get(indicator.block) {
+            Ok(block_transform) => block_transform,
+            Err(_) => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        if visibility.is_visible != camera_orbit.orthographic {
+            visibility.is_visible = camera_orbit.orthographic;
+        }
+        if !camera_orbit.orthographic {
+            continue;
+        }
+
+        let center = block_transform.translation;
+        *transform = Transform::from_translation(vec3(
+            center.x + indicator.side.x * INDICATOR_OFFSET,
+            INDICATOR_HEIGHT,
+            center.z + indicator.side.z * INDICATOR_OFFSET,
+        ))
+        .with_rotation(Quat::from_rotation_x(-FRAC_PI_2));
+
+        // face of the block looking toward the side
+        let local = block_transform.rotation.inverse() * indicator.side;
+        let face = match local.abs().max_element() {
+            max if max == local.z.abs() => match local.z > 0.0 {
+                true => 0,
+                false => 1,
+            },
+            max if max == local.x.abs() => match local.x > 0.0 {
+                true => 2,
+                false => 3,
+            },
+            _ => match local.y > 0.0 {
+                true => 4,
+                false => 5,
+            },
+        };
+        if *mesh != face_meshes.0[face] {
+            *mesh = face_meshes.0[face].clone();
+        }
+    }
+}
@@ -5,18 +5,18 @@ use std::{
 };
 
 use bevy::{math::vec3, prelude::*, utils::HashMap};
+use wasm_bindgen::JsValue;
 
 #[cfg(feature = "debug")]
 use bevy_inspector_egui::Inspectable;
-use bevy_mod_picking::PickingCameraBundle;
-use serde::{Deserialize, Serialize};
 
 use crate::{
     block::{spawn_meshes, Block, BlockMesh},
-    buffered_input::{InputBuffer, MoveImmediate},
+    buffered_input::InputBuffer,
     local_storage::LocalStorage,
-    network::NetworkChannel,
-    player::{PlayerInfo, PlayerState},
+    network::{BoardString, NetworkChannel, PlayerToken},
+    player::{PlayerIdentity, PlayerInfo, PlayerState},
+    settings::{Settings, SettingsChangedEvent},
     utils::{shuffle, string_to_board},
 };
 
@@ -25,18 +25,6 @@ pub const MIN_BOARD_SIZE: usize = 2;
 pub const MAX_BOARD_SIZE: usize = 8;
 const BLOCK_MOVE_TIME: f32 = 0.3;
 
-#[derive(Resource, Serialize, Deserialize)]
-pub struct BoardSize(pub usize);
-
-impl Default for BoardSize {
-    fn default() -> Self {
-        Self(INITIAL_BOARD_SIZE)
-    }
-}
-
-#[derive(Resource, Serialize, Deserialize)]
-pub struct EasyMode(pub bool);
-
 #[derive(Resource, Default)]
 pub struct MoveTimer(pub Timer);
 
@@ -56,12 +44,25 @@ pub struct GameState {
     pub z: i32,
     pub board: Board,
     pub is_shuffled: bool,
+    /// board right after shuffle or load, replayed by server to verify score
+    #[cfg_attr(feature = "debug", inspectable(ignore))]
+    pub initial_board: Option<BoardString>,
+    /// id of the board shuffled by server, needed to enroll a leaderboard score
+    pub leaderboard_attempt: Option<u64>,
 }
 
 #[derive(Default, Component)]
 #[cfg_attr(feature = "debug", derive(Inspectable))]
 pub struct Board(pub Vec<Vec<Option<Block>>>);
 
+/// Placement of a block compared to the solved board
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockStatus {
+    Correct,
+    Misoriented,
+    Misplaced,
+}
+
 #[derive(SystemLabel)]
 pub enum GameStages {
     UpdateBlock,
@@ -73,10 +74,9 @@ pub struct UrlReqestInfo(Option<String>);
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(LocalStorage::get_board_size().unwrap_or_default())
-            .insert_resource(LocalStorage::get_easy_mode().unwrap_or(EasyMode(false)))
-            .add_startup_system(setup)
+        app.add_startup_system(setup)
             .add_system(update_block.label(GameStages::UpdateBlock))
+            .add_system(apply_board_settings)
             .add_system_set(
                 SystemSet::on_update(PlayerState::Solving).with_system(
                     check_clear
@@ -109,6 +109,8 @@ impl GameState {
         self.x = size as i32 - 1;
         self.z = size as i32 - 1;
         self.is_shuffled = false;
+        self.initial_board = None;
+        self.leaderboard_attempt = None;
     }
 
     /// swap `self.board.0[x0][z0]` and `self.board.0[x1][z1]`
@@ -195,6 +197,29 @@ impl GameState {
         self.x = self.size as i32 - 1;
         self.z = self.size as i32 - 1;
         self.is_shuffled = false;
+        self.initial_board = None;
+        self.leaderboard_attempt = None;
+    }
+
+    /// Status of `block` placed at (x, z), blocks turned half around are also correct in easy mode
+    pub fn block_status(
+        &self,
+        x: usize,
+        z: usize,
+        block: &Block,
+        transform: &Transform,
+        easy_mode: bool,
+    ) -> BlockStatus {
+        let goal = (z * self.size + x + 1) as i32 % (self.size * self.size) as i32;
+        if block.goal != goal {
+            BlockStatus::Misplaced
+        } else if transform.rotation.is_near_identity()
+            || (easy_mode && (Quat::from_rotation_y(PI) * transform.rotation).is_near_identity())
+        {
+            BlockStatus::Correct
+        } else {
+            BlockStatus::Misoriented
+        }
     }
 
     pub fn shuffle(&mut self, transforms: &mut Query<&mut Transform>) {
@@ -209,11 +234,19 @@ fn setup(
     meshes: ResMut<Assets<Mesh>>,
     materials: ResMut<Assets<StandardMaterial>>,
     mut move_timer: ResMut<MoveTimer>,
-    board_size: Res<BoardSize>,
+    settings: Res<Settings>,
 ) {
-    let mesh_entities = spawn_meshes(&mut commands, board_size.0, meshes, materials, asset_server);
+    let board_size = settings.board_size;
+    let mesh_entities = spawn_meshes(
+        &mut commands,
+        board_size,
+        settings.theme.palette,
+        meshes,
+        materials,
+        asset_server,
+    );
     let mut new_game = GameState::default();
-    new_game.init(board_size.0, &mesh_entities);
+    new_game.init(board_size, &mesh_entities);
 
     *move_timer = MoveTimer(Timer::from_seconds(0.1, TimerMode::Once));
 
@@ -252,32 +285,47 @@ fn setup(
         },
         ..default()
     });
+}
 
-    // camera
-    commands.spawn((
-        Camera3dBundle {
-            transform: Transform::from_xyz(
-                (board_size.0 as f32 - 1.0) / 2.0,
-                board_size.0 as f32 * 1.25,
-                (board_size.0 as f32 - 1.0) / 2.0 + board_size.0 as f32 * 1.25,
-            )
-            .looking_at(
-                vec3(
-                    (board_size.0 as f32 - 1.0) / 2.0,
-                    0.0,
-                    (board_size.0 as f32 - 1.0) / 2.0,
-                ),
-                Vec3::Y,
-            ),
-            ..default()
-        },
-        PickingCameraBundle::default(),
-    ));
+/// Start a new board when board size or difficulty settings changed
+fn apply_board_settings(
+    mut commands: Commands,
+    mut settings_changed_event: EventReader<SettingsChangedEvent>,
+    settings: Res<Settings>,
+    mut player_state: ResMut<State<PlayerState>>,
+    mut game_query: Query<&mut GameState>,
+    block_mesh_query: Query<Entity, With<BlockMesh>>,
+    asset_server: Res<AssetServer>,
+    meshes: ResMut<Assets<Mesh>>,
+    materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !settings_changed_event.iter().any(|event| {
+        event.previous.board_size != settings.board_size
+            || event.previous.easy_mode != settings.easy_mode
+    }) {
+        return;
+    }
+    let board_size = settings.board_size;
+
+    // spawn new meshes
+    block_mesh_query.for_each(|entity| commands.entity(entity).despawn());
+    let mesh_entities = spawn_meshes(
+        &mut commands,
+        board_size,
+        settings.theme.palette,
+        meshes,
+        materials,
+        asset_server,
+    );
+    game_query.single_mut().init(board_size, &mesh_entities);
+    // also closes the popup the settings were changed in
+    let _ = player_state.replace(PlayerState::Idle);
 }
 
 pub fn try_url_load(
     mut player_state: ResMut<State<PlayerState>>,
     network_channel: Res<NetworkChannel>,
+    mut player_identity: ResMut<PlayerIdentity>,
 ) {
     let window = web_sys::window().unwrap();
     let query = window.location().search().map(|raw_url| {
@@ -286,7 +334,18 @@ pub fn try_url_load(
         url_key
     });
     if let Ok(url_key) = query {
-        if !url_key.is_empty() {
+        // player link: use player identity of another device
+        if let Some(link_key) = url_key.strip_prefix("player=") {
+            if let Some(player_token) = PlayerToken::from_link_key(link_key) {
+                LocalStorage::set_player_token(&player_token);
+                player_identity.0 = player_token;
+                info!("Player identity loaded");
+            }
+            // keep the secret out of the address bar and the history
+            if let (Ok(history), Ok(pathname)) = (window.history(), window.location().pathname()) {
+                let _ = history.replace_state_with_url(&JsValue::NULL, "", Some(&pathname));
+            }
+        } else if !url_key.is_empty() {
             crate::network::Network::get_puzzle_state(url_key, &mut player_state, &network_channel);
             return;
         }
@@ -302,13 +361,21 @@ fn update_block(
     mut move_timer: ResMut<MoveTimer>,
     mut game_query: Query<&mut GameState>,
     mut input_buffer: Query<&mut InputBuffer>,
-    move_immediate: Query<&MoveImmediate>,
+    settings: Res<Settings>,
     mut player_info: Query<&mut PlayerInfo>,
     mut player_state: ResMut<State<PlayerState>>,
 ) {
     let mut game = game_query.single_mut();
 
-    let timer_finished = move_timer.0.tick(time.delta()).just_finished();
+    // a buffered input snaps the rolling block to its end when interrupting is on
+    let delta = match settings.interrupt_animation
+        && !input_buffer.single().is_empty()
+        && !move_timer.0.finished()
+    {
+        true => move_timer.0.remaining(),
+        false => time.delta(),
+    };
+    let timer_finished = move_timer.0.tick(delta).just_finished();
     let elapsed_secs = move_timer.0.elapsed_secs();
 
     let mut new_move_flag = true;
@@ -355,7 +422,7 @@ fn update_block(
             game.move_block(
                 input.dx(),
                 input.dy(),
-                move_immediate.single().0,
+                settings.move_immediate,
                 &mut move_timer,
                 &mut transforms,
             );
@@ -367,7 +434,7 @@ fn check_clear(
     block_transforms: Query<&Transform, With<BlockMesh>>,
     mut app_state: ResMut<State<PlayerState>>,
     game_query: Query<&GameState>,
-    easy_mode: Res<EasyMode>,
+    settings: Res<Settings>,
 ) {
     let game = game_query.single();
 
@@ -375,39 +442,20 @@ fn check_clear(
         return;
     }
 
-    let check_block_rotation = |mut transform: Transform| {
-        if transform.rotation.is_near_identity() {
-            true
-        } else if easy_mode.0 {
-            transform.rotate_y(PI);
-            transform.rotation.is_near_identity()
-        } else {
-            false
-        }
-    };
-
-    let mut is_clear = true;
-    for x in 0..game.size {
-        for z in 0..game.size {
-            let curr = (z * game.size + x + 1) as i32 % (game.size * game.size) as i32;
-            if let Some(block) = &game.board.0[x][z] {
-                if (block.goal == curr) && block.moving.is_none() {
-                    if let Ok(block_transform) = block_transforms.get(block.entity) {
-                        if !check_block_rotation(*block_transform) {
-                            is_clear = false;
-                            break;
-                        }
-                    }
-                } else {
-                    is_clear = false;
-                    break;
-                }
-            } else if (x != game.size - 1) || (z != game.size - 1) {
-                is_clear = false;
-                break;
+    let is_clear = (0..game.size).all(|x| {
+        (0..game.size).all(|z| match &game.board.0[x][z] {
+            Some(block) => {
+                block.moving.is_none()
+                    && block_transforms
+                        .get(block.entity)
+                        .map_or(false, |transform| {
+                            game.block_status(x, z, block, transform, settings.easy_mode)
+                                == BlockStatus::Correct
+                        })
             }
-        }
-    }
+            None => x == game.size - 1 && z == game.size - 1,
+        })
+    });
 
     if is_clear {
         let _ = app_state.set(PlayerState::Clear);
@@ -1,90 +1,264 @@
+use std::{cell::Cell, collections::HashSet};
+
+use bevy::prelude::*;
+use chrono::Local;
+use serde::{de::DeserializeOwned, Serialize};
+
 use crate::{
-    daily_puzzle_info::ClearHistory,
-    game::{BoardSize, EasyMode},
+    daily_puzzle_info::{ClearHistory, DailyPuzzleCache},
+    network::PlayerToken,
+    settings::Settings,
     statistics_manager::StatisticsManager,
-    ui::SkipHowToPlay,
+    ui::GameMode,
 };
 
 const INPUT_INVERSION: &str = "input_inversion";
 const MOVE_IMMEDIATE: &str = "move_immediate";
 const DAILY_PUZZLE_CLEAR_HISTORY: &str = "daily_puzzle_clear_history";
+const DAILY_PUZZLE_CACHE: &str = "daily_puzzle_cache";
 const STATISTICS: &str = "statistics";
 const SKIP_HOW_TO_PLAY: &str = "skip_how_to_play";
 const BOARD_SIZE: &str = "board_size";
 const EASY_MODE: &str = "easy_mode";
+const PLAYER_TOKEN: &str = "player_token";
+const SETTINGS: &str = "settings";
+const SCHEMA_VERSION: &str = "schema_version";
+const BACKUP_SUFFIX: &str = "_backup";
+
+/// Version of the stored data, data without a version key is version 0
+const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// `MIGRATIONS[i]` migrates stored data from version `i` to `i + 1`
+const MIGRATIONS: [fn(); CURRENT_SCHEMA_VERSION as usize] =
+    [LocalStorage::migrate_v0, LocalStorage::migrate_v1];
+
+thread_local! {
+    // whether the stored data is of a newer game, known once migrated.
+    // the game runs on a single thread, and tests each on their own
+    static NEWER_SCHEMA: Cell<Option<bool>> = Cell::new(None);
+}
 
 pub struct LocalStorage;
 
 impl LocalStorage {
+    #[cfg(not(test))]
     fn get(key: &str) -> Option<String> {
         let local_storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
-        match local_storage.get_item(key) {
-            Ok(value) => value,
-            Err(_) => todo!(),
-        }
+        local_storage.get_item(key).unwrap_or(None)
     }
 
+    #[cfg(not(test))]
     fn set(key: &str, value: &str) {
         let local_storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
         local_storage.set_item(key, value).unwrap();
     }
 
-    pub fn get_input_inversion() -> Option<bool> {
-        Self::get(INPUT_INVERSION).map(|value| serde_json::from_str(&value).unwrap())
+    #[cfg(not(test))]
+    fn remove(key: &str) {
+        let local_storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
+        local_storage.remove_item(key).unwrap();
+    }
+
+    #[cfg(test)]
+    fn get(key: &str) -> Option<String> {
+        tests::STORAGE.with(|storage| storage.borrow().get(key).cloned())
+    }
+
+    #[cfg(test)]
+    fn set(key: &str, value: &str) {
+        tests::STORAGE.with(|storage| storage.borrow_mut().insert(key.into(), value.into()));
+    }
+
+    #[cfg(test)]
+    fn remove(key: &str) {
+        tests::STORAGE.with(|storage| storage.borrow_mut().remove(key));
+    }
+
+    fn is_newer_schema() -> bool {
+        NEWER_SCHEMA.with(|newer| newer.get() == Some(true))
+    }
+
+    /// Migrate stored data to the current version once, before it is first accessed
+    fn migrate() {
+        if NEWER_SCHEMA.with(|newer| newer.get()).is_some() {
+            return;
+        }
+        let version = Self::read_value::<u32>(SCHEMA_VERSION).unwrap_or(0);
+        let newer = version > CURRENT_SCHEMA_VERSION;
+        NEWER_SCHEMA.with(|newer_schema| newer_schema.set(Some(newer)));
+        if newer {
+            warn!("local storage version {} is newer than this game", version);
+            return;
+        }
+        for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            info!("migrate local storage from version {}", from);
+            migration();
+        }
+        Self::write_value(SCHEMA_VERSION, &CURRENT_SCHEMA_VERSION);
+    }
+
+    /// Legacy daily puzzle clear calendar and statistics are rewritten in the record formats
+    fn migrate_v0() {
+        if let Some(clear_history) = Self::read_value::<ClearHistory>(DAILY_PUZZLE_CLEAR_HISTORY) {
+            Self::write_value(DAILY_PUZZLE_CLEAR_HISTORY, &clear_history);
+        }
+        if let Some(statistics) = Self::read_value::<StatisticsManager>(STATISTICS) {
+            Self::write_value(STATISTICS, &statistics);
+        }
+    }
+
+    /// Settings stored under separate keys are gathered into one `Settings`
+    fn migrate_v1() {
+        let mut settings = Settings::default();
+        if let Some(value) = Self::read_value(MOVE_IMMEDIATE) {
+            settings.move_immediate = value;
+        }
+        if let Some(value) = Self::read_value(INPUT_INVERSION) {
+            settings.input_inversion = value;
+        }
+        if let Some(value) = Self::read_value(BOARD_SIZE) {
+            settings.board_size = value;
+        }
+        if let Some(value) = Self::read_value(EASY_MODE) {
+            settings.easy_mode = value;
+        }
+        if let Some(value) = Self::read_value::<HashSet<GameMode>>(SKIP_HOW_TO_PLAY) {
+            settings.skip_how_to_play = value;
+        }
+        Self::write_value(SETTINGS, &settings);
+        for key in [
+            MOVE_IMMEDIATE,
+            INPUT_INVERSION,
+            BOARD_SIZE,
+            EASY_MODE,
+            SKIP_HOW_TO_PLAY,
+        ] {
+            Self::remove(key);
+        }
+    }
+
+    /// Unreadable data is moved to a backup key stamped with the time, so the caller falls back
+    /// to the default. Data of a newer schema is left in place.
+    fn read_value<T: DeserializeOwned>(key: &str) -> Option<T> {
+        let value = Self::get(key)?;
+        match serde_json::from_str(&value) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                warn!("unreadable local storage {}: {}", key, err);
+                if !Self::is_newer_schema() {
+                    let timestamp = Local::now().format("%Y%m%d%H%M%S%3f");
+                    Self::set(&format!("{}{}_{}", key, BACKUP_SUFFIX, timestamp), &value);
+                    Self::remove(key);
+                }
+                None
+            }
+        }
     }
 
-    pub fn set_input_inversion(value: &bool) {
-        Self::set(INPUT_INVERSION, &serde_json::to_string(value).unwrap());
+    fn write_value<T: Serialize>(key: &str, value: &T) {
+        Self::set(key, &serde_json::to_string(value).unwrap());
     }
 
-    pub fn get_move_immediate() -> Option<bool> {
-        Self::get(MOVE_IMMEDIATE).map(|value| serde_json::from_str(&value).unwrap())
+    fn get_value<T: DeserializeOwned>(key: &str) -> Option<T> {
+        Self::migrate();
+        Self::read_value(key)
     }
 
-    pub fn set_move_immediate(value: &bool) {
-        Self::set(MOVE_IMMEDIATE, &serde_json::to_string(value).unwrap());
+    /// Changes are not saved over the data of a newer game
+    fn set_value<T: Serialize>(key: &str, value: &T) {
+        Self::migrate();
+        if !Self::is_newer_schema() {
+            Self::write_value(key, value);
+        }
     }
 
     pub fn get_daily_puzzle_clear_history() -> Option<ClearHistory> {
-        Self::get(DAILY_PUZZLE_CLEAR_HISTORY).map(|value| serde_json::from_str(&value).unwrap())
+        Self::get_value(DAILY_PUZZLE_CLEAR_HISTORY)
     }
 
     pub fn set_daily_puzzle_clear_history(value: &ClearHistory) {
-        Self::set(
-            DAILY_PUZZLE_CLEAR_HISTORY,
-            &serde_json::to_string(value).unwrap(),
-        );
+        Self::set_value(DAILY_PUZZLE_CLEAR_HISTORY, value);
+    }
+
+    pub fn get_daily_puzzle_cache() -> Option<DailyPuzzleCache> {
+        Self::get_value(DAILY_PUZZLE_CACHE)
+    }
+
+    pub fn set_daily_puzzle_cache(value: &DailyPuzzleCache) {
+        Self::set_value(DAILY_PUZZLE_CACHE, value);
     }
 
     pub fn get_statistics() -> Option<StatisticsManager> {
-        Self::get(STATISTICS).map(|value| serde_json::from_str(&value).unwrap())
+        Self::get_value(STATISTICS)
     }
 
     pub fn set_statistics(value: &StatisticsManager) {
-        Self::set(STATISTICS, &serde_json::to_string(value).unwrap());
+        Self::set_value(STATISTICS, value);
+    }
+
+    pub fn get_settings() -> Option<Settings> {
+        Self::get_value(SETTINGS)
     }
 
-    pub fn get_skip_how_to_play() -> Option<SkipHowToPlay> {
-        Self::get(SKIP_HOW_TO_PLAY).map(|value| serde_json::from_str(&value).unwrap())
+    pub fn set_settings(value: &Settings) {
+        Self::set_value(SETTINGS, value);
     }
 
-    pub fn set_skip_how_to_play(value: &SkipHowToPlay) {
-        Self::set(SKIP_HOW_TO_PLAY, &serde_json::to_string(value).unwrap());
+    pub fn get_player_token() -> Option<PlayerToken> {
+        Self::get_value(PLAYER_TOKEN)
     }
 
-    pub fn get_board_size() -> Option<BoardSize> {
-        Self::get(BOARD_SIZE).map(|value| serde_json::from_str(&value).unwrap())
+    pub fn set_player_token(value: &PlayerToken) {
+        Self::set_value(PLAYER_TOKEN, value);
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashMap};
+
+    use super::*;
 
-    pub fn set_board_size(value: &BoardSize) {
-        Self::set(BOARD_SIZE, &serde_json::to_string(value).unwrap());
+    thread_local! {
+        pub static STORAGE: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
     }
 
-    pub fn get_easy_mode() -> Option<EasyMode> {
-        Self::get(EASY_MODE).map(|value| serde_json::from_str(&value).unwrap())
+    fn stored_keys() -> Vec<String> {
+        let mut keys: Vec<_> = STORAGE.with(|storage| storage.borrow().keys().cloned().collect());
+        keys.sort();
+        keys
     }
 
-    pub fn set_easy_mode(value: &EasyMode) {
-        Self::set(EASY_MODE, &serde_json::to_string(value).unwrap());
+    #[test]
+    fn data_of_a_newer_schema_is_kept() {
+        let newer_version = (CURRENT_SCHEMA_VERSION + 1).to_string();
+        LocalStorage::set(SCHEMA_VERSION, &newer_version);
+        LocalStorage::set(SETTINGS, "newer settings");
+
+        assert!(LocalStorage::get_settings().is_none());
+        LocalStorage::set_settings(&Settings::default());
+        LocalStorage::set_player_token(&PlayerToken::generate());
+
+        assert_eq!(LocalStorage::get(SETTINGS).unwrap(), "newer settings");
+        assert_eq!(LocalStorage::get(SCHEMA_VERSION).unwrap(), newer_version);
+        assert_eq!(stored_keys(), [SCHEMA_VERSION, SETTINGS]);
+    }
+
+    #[test]
+    fn unreadable_data_is_backed_up() {
+        LocalStorage::set(SCHEMA_VERSION, &CURRENT_SCHEMA_VERSION.to_string());
+        LocalStorage::set(SETTINGS, "broken settings");
+
+        assert!(LocalStorage::get_settings().is_none());
+        let keys = stored_keys();
+        let backup = keys
+            .iter()
+            .find(|key| key.starts_with(&format!("{}{}_", SETTINGS, BACKUP_SUFFIX)))
+            .unwrap();
+        assert_eq!(LocalStorage::get(backup).unwrap(), "broken settings");
+        assert!(LocalStorage::get(SETTINGS).is_none());
+
+        LocalStorage::set_settings(&Settings::default());
+        assert!(LocalStorage::get_settings().is_some());
     }
 }
@@ -1,11 +1,16 @@
 mod block;
 mod buffered_input;
+mod camera;
 mod daily_puzzle_info;
+mod face_indicator;
 mod game;
 mod local_storage;
 mod network;
+mod orientation_assist;
 mod player;
+mod settings;
 mod statistics_manager;
+mod theme;
 mod ui;
 mod utils;
 
@@ -13,11 +18,16 @@ use bevy::{prelude::*, DefaultPlugins};
 use bevy_mod_picking::DefaultPickingPlugins;
 use buffered_input::CustomInputPlugin;
 use buffered_input::InputTimer;
+use camera::CameraPlugin;
 use daily_puzzle_info::DailyPuzzleInfoPlugin;
+use face_indicator::FaceIndicatorPlugin;
 use game::{GamePlugin, MoveTimer};
 use network::NetworkPlugin;
+use orientation_assist::OrientationAssistPlugin;
 use player::PlayerPlugin;
+use settings::SettingsPlugin;
 use statistics_manager::StatisticsManagerPlugin;
+use theme::ThemePlugin;
 use ui::*;
 
 #[cfg(not(feature = "debug"))]
@@ -28,8 +38,13 @@ fn main() {
         .add_plugins(DefaultPlugins)
         .add_plugins(DefaultPickingPlugins)
         .add_plugin(NetworkPlugin)
+        .add_plugin(SettingsPlugin)
         .add_plugin(PlayerPlugin)
         .add_plugin(GamePlugin)
+        .add_plugin(CameraPlugin)
+        .add_plugin(FaceIndicatorPlugin)
+        .add_plugin(OrientationAssistPlugin)
+        .add_plugin(ThemePlugin)
         .add_plugin(GameUIPlugin)
         .add_plugin(CustomInputPlugin)
         .add_plugin(PopupPlugin)
@@ -49,8 +64,13 @@ fn main() {
         .add_plugins(DefaultPlugins)
         .add_plugin(WorldInspectorPlugin::new())
         .register_inspectable::<GameState>()
+        .add_plugin(SettingsPlugin)
         .add_plugin(PlayerPlugin)
         .add_plugin(GamePlugin)
+        .add_plugin(CameraPlugin)
+        .add_plugin(FaceIndicatorPlugin)
+        .add_plugin(OrientationAssistPlugin)
+        .add_plugin(ThemePlugin)
         .add_plugin(GameUIPlugin)
         .run();
 }
@@ -6,19 +6,73 @@ use std::{
     time::Duration,
 };
 
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
 /// (position, rotation)
 /// index: number written on block (0 means empty)
 pub struct BoardString(pub Vec<(u8, u8)>);
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// One enrolled score. Tied entries share `rank` and the next rank is skipped (1, 2, 2, 4)
+pub struct RankingEntry {
+    pub rank: usize,
+    pub name: String,
+    pub time_micros: i64, // Duration.to_micros()
+    pub moves: usize,
+    pub submitted_at: Option<NaiveDateTime>, // None for scores enrolled before it was recorded
+    pub assisted: bool,                      // undo or hint used
+}
+
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct DailyRanking {
     pub date: NaiveDate,
-    pub time_ranking: Vec<(String, i64)>, //i64 => Duration.to_micros()
-    pub move_ranking: Vec<(String, f32)>,
+    pub time_ranking: Vec<RankingEntry>,
+    pub move_ranking: Vec<RankingEntry>,
+    pub composite_ranking: Vec<RankingEntry>, // moves, then time
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LeaderboardMode {
+    TimeAttack,
+    MinimalMovement,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Leaderboards are segmented by mode, board size and easy/hard
+pub struct LeaderboardKey {
+    pub mode: LeaderboardMode,
+    pub size: usize,
+    pub easy_mode: bool,
+}
+
+/// Server never returns more entries than this in a `RankingPage`
+pub const MAX_RANKING_PAGE_SIZE: usize = 100;
+
+/// Public salt of `BoardString::daily_puzzle`, changing it changes every daily puzzle
+pub const DAILY_PUZZLE_SALT: u64 = 0x6469_6365_3135_7075; // "dice15pu"
+pub const DAILY_PUZZLE_SIZE: usize = 4;
+const DAILY_PUZZLE_SHUFFLE_NUMBER: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+/// Every ranking which can be requested page by page
+pub enum RankingKind {
+    DailyTime(NaiveDate),
+    DailyMove(NaiveDate),
+    DailyComposite(NaiveDate), // moves, then time
+    AllTime(LeaderboardKey),
+    Weekly(LeaderboardKey),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Part of a ranking, `entries[0]` is ranked `offset + 1`
+pub struct RankingPage {
+    pub kind: RankingKind,
+    pub offset: usize,
+    pub total: usize,
+    pub entries: Vec<RankingEntry>,
+    pub my_rank: Option<usize>, // index of caller in whole ranking, `GetMyRank` only
 }
 
 #[derive(Copy, Serialize, Deserialize, Clone, Debug)]
@@ -26,6 +80,57 @@ pub enum NetworkError {
     KeyAlreadyExist,
     NoEntry,
     NameAlreadyExist,
+    InvalidScore,
+    Unauthorized,
+    AlreadyEnrolled,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// Anonymous player identity, generated on first launch.
+/// Server binds a display name to `id`, `secret` authenticates the player.
+pub struct PlayerToken {
+    pub id: String,
+    pub secret: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+/// Direction the empty cell moves to, same as `GameInput`
+pub enum Move {
+    Up,    // (0, 1)
+    Down,  // (0, -1)
+    Left,  // (1, 0)
+    Right, // (-1, 0)
+}
+
+impl Move {
+    pub fn dx(&self) -> i32 {
+        match self {
+            Move::Left => 1,
+            Move::Right => -1,
+            _ => 0,
+        }
+    }
+
+    pub fn dz(&self) -> i32 {
+        match self {
+            Move::Up => 1,
+            Move::Down => -1,
+            _ => 0,
+        }
+    }
+}
+
+/// SplitMix64, unlike `rand` its sequence is fixed for a seed on every platform
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
 }
 
 #[allow(dead_code)]
@@ -37,6 +142,43 @@ impl BoardString {
         Self(vec![(0, 0); size * size])
     }
 
+    /// Every block at its goal facing up, empty cell at the last position
+    pub fn solved(size: usize) -> BoardString {
+        let mut board_string = Self::new(size);
+        board_string.0[0] = ((size * size) as u8, 0);
+        for i in 1..size * size {
+            board_string.0[i] = (i as u8, encode_rotation([0.0, 0.0, 0.0, 1.0]));
+        }
+        board_string
+    }
+
+    /// Daily puzzle of `date`, seeded by the date and `DAILY_PUZZLE_SALT`.
+    /// Server generates daily puzzles with this, so an offline client gets the same one.
+    pub fn daily_puzzle(date: NaiveDate) -> BoardString {
+        Self::shuffled(
+            DAILY_PUZZLE_SIZE,
+            DAILY_PUZZLE_SALT ^ date.num_days_from_ce() as u64,
+            DAILY_PUZZLE_SHUFFLE_NUMBER,
+        )
+    }
+
+    /// Solved board rolled by `shuffle_number` random moves, the same for `seed` on every platform
+    pub fn shuffled(size: usize, seed: u64, shuffle_number: usize) -> BoardString {
+        let mut rng = SplitMix64(seed);
+        let mut board_string = Self::solved(size);
+        for _ in 0..shuffle_number {
+            board_string
+                .apply_move(match rng.next() % 4 {
+                    0 => Move::Up,
+                    1 => Move::Down,
+                    2 => Move::Left,
+                    _ => Move::Right,
+                })
+                .expect("every cell of a solved board is filled");
+        }
+        board_string
+    }
+
     fn into_hash(&self) -> u64 {
         let mut s = DefaultHasher::new();
         self.hash(&mut s);
@@ -77,6 +219,55 @@ impl BoardString {
         vec
     }
 
+    pub fn size(&self) -> usize {
+        (self.0.len() as f64).sqrt() as usize
+    }
+
+    /// Roll the block next to the empty cell, same as `GameState::move_block`.
+    /// Moves toward the wall are ignored.
+    /// `NetworkError::InvalidScore` if the board has no empty cell or no block to roll
+    pub fn apply_move(&mut self, game_move: Move) -> Result<(), NetworkError> {
+        let size = self.size() as i32;
+        let empty = match self.0.first() {
+            Some((position, _)) if (1..=self.0.len()).contains(&(*position as usize)) => {
+                *position as i32 - 1
+            }
+            _ => return Err(NetworkError::InvalidScore),
+        };
+        let (dx, dz) = (game_move.dx(), game_move.dz());
+        let (x, z) = (empty % size, empty / size);
+        if x + dx < 0 || x + dx >= size || z + dz < 0 || z + dz >= size {
+            return Ok(());
+        }
+
+        let position = ((z + dz) * size + (x + dx) + 1) as u8;
+        let i = match self.0.iter().skip(1).position(|(p, _)| *p == position) {
+            Some(i) => i + 1,
+            None => return Err(NetworkError::InvalidScore),
+        };
+        // same as `rotate_x(-dz * PI / 2)` and `rotate_z(dx * PI / 2)` of bevy Transform
+        let (s, c) = (
+            std::f32::consts::FRAC_PI_4.sin(),
+            std::f32::consts::FRAC_PI_4.cos(),
+        );
+        let roll = [-dz as f32 * s, 0.0, dx as f32 * s, c];
+        let rotation = quat_mul(roll, decode_rotation(self.0[i].1));
+        self.0[i] = (self.0[0].0, encode_rotation(rotation));
+        self.0[0].0 = position;
+        Ok(())
+    }
+
+    /// Every block is at its goal and faces up.
+    /// In easy mode, blocks rotated 180 degrees around y axis are also accepted.
+    pub fn is_solved(&self, easy_mode: bool) -> bool {
+        (1..self.0.len()).all(|i| {
+            let (position, rotation) = self.0[i];
+            let [_, y, _, w] = decode_rotation(rotation);
+            let upright = w.abs() > 0.99 || (easy_mode && y.abs() > 0.99);
+            position as usize == i && upright
+        })
+    }
+
     pub fn from_arr(query_result: &Vec<u8>) -> Self {
         let size = (query_result.len() as f64).sqrt() as usize;
         let mut board_string = BoardString::new(size);
@@ -88,6 +279,106 @@ impl BoardString {
     }
 }
 
+/// rotation byte of `BoardString` into normalized quaternion (x, y, z, w)
+fn decode_rotation(rotation: u8) -> [f32; 4] {
+    let mut arr = [0.0; 4];
+    (0..4).for_each(|j| {
+        let bits = rotation >> ((3 - j) * 2); // 0, 1, or 3
+        let num = (bits & 1) as f32;
+        let sign = -((bits & 2) as f32) + 1.0; // 1 or -1
+        arr[j] = sign * num;
+    });
+    let norm = arr.iter().map(|v| v * v).sum::<f32>().sqrt();
+    arr.map(|v| v / norm)
+}
+
+/// quaternion (x, y, z, w) into rotation byte of `BoardString`
+fn encode_rotation(rotation: [f32; 4]) -> u8 {
+    rotation.iter().fold(0_u8, |cur, &i| {
+        (cur << 2)
+            | if i > 0.3 {
+                1
+            } else if i < -0.3 {
+                3
+            } else {
+                0
+            }
+    })
+}
+
+/// Hamilton product `a * b` of quaternions (x, y, z, w)
+fn quat_mul(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    let [ax, ay, az, aw] = a;
+    let [bx, by, bz, bw] = b;
+    [
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+        aw * bw - ax * bx - ay * by - az * bz,
+    ]
+}
+
+/// Replay `move_log` on the initial board and check the enrolled move count.
+/// Used by server before enrolling daily and leaderboard scores.
+#[allow(dead_code)]
+pub fn verify_score(
+    board_string: &BoardString,
+    move_log: &[Move],
+    moves: usize,
+    easy_mode: bool,
+) -> Result<(), NetworkError> {
+    if move_log.len() != moves {
+        return Err(NetworkError::InvalidScore);
+    }
+    let mut board_string = board_string.clone();
+    for game_move in move_log {
+        board_string.apply_move(*game_move)?;
+    }
+    if board_string.is_solved(easy_mode) {
+        Ok(())
+    } else {
+        Err(NetworkError::InvalidScore)
+    }
+}
+
+#[allow(dead_code)]
+impl PlayerToken {
+    const ID_LEN: usize = 12;
+    const SECRET_LEN: usize = 24;
+
+    pub fn generate() -> Self {
+        // alphanumeric part of `BoardString::CORPUS` only, to be used in URL
+        let random_string = |len: usize| {
+            (0..len)
+                .map(|_| BoardString::CORPUS[rand::random::<usize>() % 62] as char)
+                .collect()
+        };
+        Self {
+            id: random_string(Self::ID_LEN),
+            secret: random_string(Self::SECRET_LEN),
+        }
+    }
+
+    /// "id.secret", used to move identity to another device by URL
+    pub fn to_link_key(&self) -> String {
+        format!("{}.{}", self.id, self.secret)
+    }
+
+    pub fn from_link_key(link_key: &str) -> Option<Self> {
+        let (id, secret) = link_key.split_once('.')?;
+        let valid =
+            |s: &str, len: usize| s.len() == len && s.chars().all(|c| c.is_ascii_alphanumeric());
+        if valid(id, Self::ID_LEN) && valid(secret, Self::SECRET_LEN) {
+            Some(Self {
+                id: id.to_string(),
+                secret: secret.to_string(),
+            })
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum RequestType {
     GetDailyPuzzle(NaiveDate),
@@ -95,9 +386,31 @@ pub enum RequestType {
     GenerateDailyPuzzle(NaiveDate), // used by daily trigger only
     EnrollPuzzleState(String, BoardString),
     GetPuzzleState(String),
-    EnrollDailyScore(NaiveDate, String, Duration, usize), // zadd 사용해서 처리하면 될듯함
-    GetDailyRanking(NaiveDate),
+    // zadd 사용해서 처리하면 될듯함
+    EnrollDailyScore(
+        NaiveDate,
+        PlayerToken,
+        String,
+        Duration,
+        usize,
+        Vec<Move>,
+        bool, // undo or hint used
+    ),
+    GetDailyRanking(NaiveDate), // whole ranking, not sent by clients since ranking pages
     ClearRanking(NaiveDate),
+    StartLeaderboardAttempt(LeaderboardKey, String), // player id
+    EnrollLeaderboardScore(
+        LeaderboardKey,
+        PlayerToken,
+        String,
+        Duration,
+        usize,
+        u64, // attempt id
+        Vec<Move>,
+        bool, // undo or hint used
+    ),
+    GetRankingPage(RankingKind, usize, usize), // offset, limit
+    GetMyRank(RankingKind, String, usize),     // player id, neighbors
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -110,4 +423,91 @@ pub enum ResponseType {
     EnrollDailyScore(Result<(), NetworkError>),
     GetDailyRanking(Result<DailyRanking, NetworkError>),
     ClearRanking(Result<(), NetworkError>),
+    StartLeaderboardAttempt(Result<(u64, BoardString), NetworkError>), // attempt id, board
+    EnrollLeaderboardScore(Result<(), NetworkError>),
+    GetRankingPage(Result<RankingPage, NetworkError>),
+    GetMyRank(Result<RankingPage, NetworkError>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// solved 3x3 board with the empty cell moved left and up, solved by `[Left, Up]`
+    fn shuffled() -> BoardString {
+        let mut board_string = BoardString::solved(3);
+        board_string.apply_move(Move::Down).unwrap();
+        board_string.apply_move(Move::Right).unwrap();
+        board_string
+    }
+
+    #[test]
+    fn replayed_solve_is_valid() {
+        let board_string = shuffled();
+        assert!(!board_string.is_solved(false));
+        assert!(verify_score(&board_string, &[Move::Left, Move::Up], 2, false).is_ok());
+    }
+
+    #[test]
+    fn wrong_move_count_is_invalid() {
+        assert!(matches!(
+            verify_score(&shuffled(), &[Move::Left, Move::Up], 3, false),
+            Err(NetworkError::InvalidScore)
+        ));
+    }
+
+    #[test]
+    fn unsolved_result_is_invalid() {
+        assert!(matches!(
+            verify_score(&shuffled(), &[Move::Left], 1, false),
+            Err(NetworkError::InvalidScore)
+        ));
+        assert!(matches!(
+            verify_score(&shuffled(), &[Move::Up, Move::Left], 2, false),
+            Err(NetworkError::InvalidScore)
+        ));
+    }
+
+    #[test]
+    fn wall_bumps_are_counted_and_ignored() {
+        let mut board_string = BoardString::solved(3);
+        board_string.apply_move(Move::Up).unwrap();
+        board_string.apply_move(Move::Left).unwrap();
+        assert_eq!(board_string, BoardString::solved(3));
+        assert!(verify_score(
+            &shuffled(),
+            &[Move::Left, Move::Left, Move::Up, Move::Up],
+            4,
+            false
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn rolled_block_keeps_rolling_back() {
+        let mut board_string = BoardString::solved(3);
+        board_string.apply_move(Move::Down).unwrap();
+        assert_ne!(board_string.0[6].1, BoardString::solved(3).0[6].1);
+        board_string.apply_move(Move::Up).unwrap();
+        assert_eq!(board_string, BoardString::solved(3));
+    }
+
+    #[test]
+    fn malformed_board_is_invalid() {
+        assert!(matches!(
+            BoardString::new(3).apply_move(Move::Down),
+            Err(NetworkError::InvalidScore)
+        ));
+        // no block at the cell above the empty one
+        let mut board_string = BoardString::solved(3);
+        board_string.0[6].0 = 5;
+        assert!(matches!(
+            board_string.apply_move(Move::Down),
+            Err(NetworkError::InvalidScore)
+        ));
+        assert!(matches!(
+            verify_score(&board_string, &[Move::Down], 1, false),
+            Err(NetworkError::InvalidScore)
+        ));
+    }
 }
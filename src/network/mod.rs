@@ -1,5 +1,11 @@
 mod lib;
 mod network;
+#[cfg(feature = "sqlite_storage")]
+#[allow(dead_code)]
+mod storage;
 
 pub use lib::*;
 pub use network::*;
+#[cfg(feature = "sqlite_storage")]
+#[allow(unused_imports)]
+pub use storage::*;
@@ -1,7 +1,7 @@
 use std::time::Duration;
 
 use bevy::{prelude::*, tasks::AsyncComputeTaskPool};
-use chrono::NaiveDate;
+use chrono::{Local, NaiveDate};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
 use crate::{
@@ -9,7 +9,7 @@ use crate::{
     game::GameState,
     network::*,
     player::PlayerState,
-    ui::{MyTextType, RankingType, ScrollBar},
+    ui::{MyTextType, RankingPageInfo, RankingType, ScrollBar},
     utils::{duration_to_string, string_to_board},
 };
 
@@ -26,9 +26,13 @@ pub struct NetworkPlugin;
 
 impl Plugin for NetworkPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(init_network_channel).add_system_set(
-            SystemSet::on_update(PlayerState::ResponseWaiting).with_system(response_waiting_system),
-        );
+        app.add_startup_system(init_network_channel)
+            .add_system_set(
+                SystemSet::on_update(PlayerState::ResponseWaiting)
+                    .with_system(response_waiting_system)
+                    .with_system(request_failed_system),
+            )
+            .add_system(prefetch_response_system);
     }
 }
 
@@ -36,6 +40,12 @@ impl Plugin for NetworkPlugin {
 pub struct NetworkChannel {
     input: UnboundedSender<RequestType>,
     output: UnboundedReceiver<ResponseType>,
+    // requests which couldn't reach the server
+    failed: UnboundedReceiver<RequestType>,
+    // background requests which don't block the player with ResponseWaiting
+    prefetch_input: UnboundedSender<RequestType>,
+    prefetch_output: UnboundedReceiver<ResponseType>,
+    prefetch_failed: UnboundedReceiver<RequestType>,
 }
 
 pub struct Network;
@@ -56,6 +66,14 @@ impl Network {
         info!("get_daily_puzzle");
     }
 
+    pub fn prefetch_daily_puzzle(date: NaiveDate, network_channel: &NetworkChannel) {
+        network_channel
+            .prefetch_input
+            .send(RequestType::GetDailyPuzzle(date))
+            .unwrap();
+        info!("prefetch_daily_puzzle {}", date);
+    }
+
     pub fn get_daily_puzzle_date(
         player_state: &mut ResMut<State<PlayerState>>,
         network_channel: &mut Res<NetworkChannel>,
@@ -98,27 +116,97 @@ impl Network {
 
     pub fn enroll_daily_ranking(
         date: NaiveDate,
+        player_token: PlayerToken,
         user_name: String,
         time: Duration,
         moves: usize,
+        move_log: Vec<Move>,
+        assisted: bool,
         player_state: &mut ResMut<State<PlayerState>>,
         network_channel: &Res<NetworkChannel>,
     ) {
         network_channel
             .input
-            .send(RequestType::EnrollDailyScore(date, user_name, time, moves))
+            .send(RequestType::EnrollDailyScore(
+                date,
+                player_token,
+                user_name,
+                time,
+                moves,
+                move_log,
+                assisted,
+            ))
             .unwrap();
         player_state.push(PlayerState::ResponseWaiting).unwrap();
     }
 
-    pub fn get_daily_ranking(
-        date: NaiveDate,
+    pub fn start_leaderboard_attempt(
+        key: LeaderboardKey,
+        player_id: String,
         player_state: &mut ResMut<State<PlayerState>>,
         network_channel: &Res<NetworkChannel>,
     ) {
         network_channel
             .input
-            .send(RequestType::GetDailyRanking(date))
+            .send(RequestType::StartLeaderboardAttempt(key, player_id))
+            .unwrap();
+        player_state.push(PlayerState::ResponseWaiting).unwrap();
+    }
+
+    pub fn enroll_leaderboard_score(
+        key: LeaderboardKey,
+        player_token: PlayerToken,
+        user_name: String,
+        time: Duration,
+        moves: usize,
+        attempt: u64,
+        move_log: Vec<Move>,
+        assisted: bool,
+        player_state: &mut ResMut<State<PlayerState>>,
+        network_channel: &Res<NetworkChannel>,
+    ) {
+        network_channel
+            .input
+            .send(RequestType::EnrollLeaderboardScore(
+                key,
+                player_token,
+                user_name,
+                time,
+                moves,
+                attempt,
+                move_log,
+                assisted,
+            ))
+            .unwrap();
+        player_state.push(PlayerState::ResponseWaiting).unwrap();
+    }
+
+    pub fn get_ranking_page(
+        kind: RankingKind,
+        offset: usize,
+        limit: usize,
+        player_state: &mut ResMut<State<PlayerState>>,
+        network_channel: &Res<NetworkChannel>,
+    ) {
+        network_channel
+            .input
+            .send(RequestType::GetRankingPage(kind, offset, limit))
+            .unwrap();
+        player_state
+            .overwrite_push(PlayerState::ResponseWaiting)
+            .unwrap();
+    }
+
+    pub fn get_my_rank(
+        kind: RankingKind,
+        player_id: String,
+        neighbors: usize,
+        player_state: &mut ResMut<State<PlayerState>>,
+        network_channel: &Res<NetworkChannel>,
+    ) {
+        network_channel
+            .input
+            .send(RequestType::GetMyRank(kind, player_id, neighbors))
             .unwrap();
         player_state
             .overwrite_push(PlayerState::ResponseWaiting)
@@ -128,31 +216,126 @@ impl Network {
 
 fn init_network_channel(mut commands: Commands) {
     info!("init response");
-    let thread_pool = AsyncComputeTaskPool::get();
-    let (req_tx, mut req_rx) = unbounded_channel::<RequestType>();
+    let (req_tx, req_rx) = unbounded_channel::<RequestType>();
     let (res_tx, res_rx) = unbounded_channel::<ResponseType>();
+    let (failed_tx, failed_rx) = unbounded_channel::<RequestType>();
+    spawn_request_loop(req_rx, res_tx, failed_tx);
+    let (prefetch_req_tx, prefetch_req_rx) = unbounded_channel::<RequestType>();
+    let (prefetch_res_tx, prefetch_res_rx) = unbounded_channel::<ResponseType>();
+    let (prefetch_failed_tx, prefetch_failed_rx) = unbounded_channel::<RequestType>();
+    spawn_request_loop(prefetch_req_rx, prefetch_res_tx, prefetch_failed_tx);
+    commands.insert_resource(NetworkChannel {
+        input: req_tx,
+        output: res_rx,
+        failed: failed_rx,
+        prefetch_input: prefetch_req_tx,
+        prefetch_output: prefetch_res_rx,
+        prefetch_failed: prefetch_failed_rx,
+    });
+}
+
+fn spawn_request_loop(
+    mut req_rx: UnboundedReceiver<RequestType>,
+    res_tx: UnboundedSender<ResponseType>,
+    failed_tx: UnboundedSender<RequestType>,
+) {
+    let thread_pool = AsyncComputeTaskPool::get();
     thread_pool.spawn(async move {
         loop {
             if let Some(req) = req_rx.recv().await {
-                let client = reqwest::Client::new();
-                let res = client
-                    .post(SERVER_ADDR)
-                    .body(serde_json::to_string(&req).unwrap())
-                    .send()
-                    .await
-                    .unwrap()
-                    .text()
-                    .await
-                    .unwrap();
-                let response_type: ResponseType = serde_json::from_str(&res).unwrap();
-                res_tx.send(response_type).unwrap();
+                match send_request(&req).await {
+                    Some(response_type) => res_tx.send(response_type).unwrap(),
+                    None => failed_tx.send(req).unwrap(),
+                }
             }
         }
     });
-    commands.insert_resource(NetworkChannel {
-        input: req_tx,
-        output: res_rx,
-    });
+}
+
+/// None if the server can't be reached, e.g. while offline
+async fn send_request(req: &RequestType) -> Option<ResponseType> {
+    let client = reqwest::Client::new();
+    let res = client
+        .post(SERVER_ADDR)
+        .body(serde_json::to_string(req).unwrap())
+        .send()
+        .await
+        .ok()?
+        .text()
+        .await
+        .ok()?;
+    serde_json::from_str(&res).ok()
+}
+
+fn prefetch_response_system(
+    mut network_channel: ResMut<NetworkChannel>,
+    mut daily_puzzle_info_query: Query<&mut DailyPuzzleInfo>,
+) {
+    while let Ok(response_type) = network_channel.prefetch_output.try_recv() {
+        match response_type {
+            ResponseType::GetDailyPuzzle(date, board_string) => {
+                daily_puzzle_info_query
+                    .single_mut()
+                    .insert_daily_puzzle(date, board_string);
+            }
+            response_type => info!("unexpected prefetch response {:?}", response_type),
+        }
+    }
+    while let Ok(request) = network_channel.prefetch_failed.try_recv() {
+        info!("prefetch failed {:?}", request);
+    }
+}
+
+/// Requests which couldn't reach the server leave the waiting state
+fn request_failed_system(
+    mut player_state: ResMut<State<PlayerState>>,
+    mut transforms: Query<&mut Transform>,
+    mut game_query: Query<&mut GameState>,
+    mut network_channel: ResMut<NetworkChannel>,
+    mut daily_puzzle_info_query: Query<&mut DailyPuzzleInfo>,
+) {
+    let mut game = game_query.single_mut();
+    let mut daily_puzzle_info = daily_puzzle_info_query.single_mut();
+
+    if let Ok(request) = network_channel.failed.try_recv() {
+        info!("request failed {:?}", request);
+        match request {
+            // offline, continue with the cached daily puzzles or generate today's one
+            RequestType::GetDailyPuzzleDate => {
+                match daily_puzzle_info.has_cache() {
+                    true => daily_puzzle_info.current_date = daily_puzzle_info.last_date,
+                    false => daily_puzzle_info.set_local_dates(Local::now().date_naive()),
+                }
+                let last_date = daily_puzzle_info.last_date;
+                daily_puzzle_info.insert_generated_daily_puzzle(last_date);
+                let _ = daily_puzzle_info.load_daily_puzzle(
+                    last_date,
+                    &mut transforms,
+                    &mut game,
+                    &mut player_state,
+                    &Res::from(network_channel),
+                );
+            }
+            RequestType::GetDailyPuzzle(date) => {
+                daily_puzzle_info.insert_generated_daily_puzzle(date);
+                let _ = daily_puzzle_info.load_daily_puzzle(
+                    date,
+                    &mut transforms,
+                    &mut game,
+                    &mut player_state,
+                    &Res::from(network_channel),
+                );
+            }
+            // offline, play a local shuffle which can't be enrolled
+            RequestType::StartLeaderboardAttempt(..) => {
+                game.shuffle(&mut transforms);
+                player_state.replace(PlayerState::Shuffled).unwrap();
+            }
+            _ => {
+                player_state.pop().unwrap();
+            }
+        }
+    }
 }
 
 fn response_waiting_system(
@@ -162,7 +345,7 @@ fn response_waiting_system(
     mut network_channel: ResMut<NetworkChannel>,
     mut daily_puzzle_info_query: Query<&mut DailyPuzzleInfo>,
     mut text_query: Query<(&mut Text, &MyTextType)>,
-    mut scroll_bar_query: Query<(&mut ScrollBar, &RankingType)>,
+    mut scroll_bar_query: Query<(&mut ScrollBar, &RankingType, &mut RankingPageInfo)>,
 ) {
     let mut game = game_query.single_mut();
     let mut daily_puzzle_info = daily_puzzle_info_query.single_mut();
@@ -182,9 +365,7 @@ fn response_waiting_system(
                 assert!(load_result);
             }
             ResponseType::GetDailyPuzzleDate { first, last } => {
-                daily_puzzle_info.first_date = first;
-                daily_puzzle_info.last_date = last;
-                daily_puzzle_info.current_date = last;
+                daily_puzzle_info.set_dates(first, last);
                 let _ = daily_puzzle_info.load_daily_puzzle(
                     last,
                     &mut transforms,
@@ -226,10 +407,24 @@ fn response_waiting_system(
                     player_state.pop().unwrap();
                 }
             },
-            ResponseType::EnrollDailyScore(result) => {
+            ResponseType::StartLeaderboardAttempt(result) => {
+                match result {
+                    Ok((attempt, board_string)) => {
+                        string_to_board(&board_string, &mut transforms, &mut game);
+                        game.leaderboard_attempt = Some(attempt);
+                    }
+                    Err(e) => {
+                        info!("Attempt Failed {:?}", e);
+                        game.shuffle(&mut transforms);
+                    }
+                }
+                player_state.replace(PlayerState::Shuffled).unwrap();
+            }
+            ResponseType::EnrollDailyScore(result)
+            | ResponseType::EnrollLeaderboardScore(result) => {
                 for (mut text, _) in text_query
                     .iter_mut()
-                    .filter(|(_, text_type)| **text_type == MyTextType::EnrollDailyScoreResult)
+                    .filter(|(_, text_type)| **text_type == MyTextType::EnrollScoreResult)
                 {
                     text.sections[0].value = match result {
                         Ok(_) => "Enroll success!",
@@ -239,53 +434,90 @@ fn response_waiting_system(
                                 "Can't enroll score of\nprevious daily puzzles"
                             }
                             NetworkError::NameAlreadyExist => "Name already exists",
+                            NetworkError::InvalidScore => "Invalid score",
+                            NetworkError::Unauthorized => "Player authentication failed",
+                            NetworkError::AlreadyEnrolled => "Already enrolled",
                         },
                     }
                     .to_string();
                 }
                 player_state.pop().unwrap();
             }
-            ResponseType::GetDailyRanking(result) => {
-                match result {
-                    Ok(ranking) => {
-                        for (mut scroll_bar, ranking_type) in scroll_bar_query.iter_mut() {
-                            let mut content = vec![];
-                            match ranking_type {
-                                RankingType::Time => {
-                                    for (i, (name, micros)) in
-                                        ranking.time_ranking.iter().enumerate()
-                                    {
-                                        content.push(format!(
-                                            "{}. {} | {}",
-                                            i + 1,
-                                            duration_to_string(Duration::from_micros(
-                                                *micros as u64
-                                            )),
-                                            name
-                                        ));
-                                    }
-                                }
-                                RankingType::Move => {
-                                    for (i, (name, moves)) in
-                                        ranking.move_ranking.iter().enumerate()
-                                    {
-                                        content.push(format!(
-                                            "{}. {} | {}",
-                                            i + 1,
-                                            moves.floor(),
-                                            name
-                                        ));
-                                    }
-                                }
-                            };
-                            scroll_bar.content = content;
+            // not requested by this client, ranking pages are used instead
+            ResponseType::GetDailyRanking(_) => info!("unexpected daily ranking response"),
+            ResponseType::ClearRanking(_) => todo!(),
+            ResponseType::GetRankingPage(result) | ResponseType::GetMyRank(result) => {
+                let page_text = match result {
+                    Ok(page) => {
+                        for (mut scroll_bar, _, mut page_info) in scroll_bar_query
+                            .iter_mut()
+                            .filter(|(_, _, page_info)| page_info.kind == page.kind)
+                        {
+                            page_info.offset = page.offset;
+                            page_info.len = page.entries.len();
+                            page_info.total = page.total;
+                            scroll_bar.content = page
+                                .entries
+                                .iter()
+                                .enumerate()
+                                .map(|(i, entry)| {
+                                    let marker = match page.my_rank == Some(page.offset + i) {
+                                        true => "> ",
+                                        false => "",
+                                    };
+                                    marker.to_string() + &ranking_entry_to_string(&page.kind, entry)
+                                })
+                                .collect();
+                            // scroll bar clamps the position
+                            scroll_bar.position = page.my_rank.map_or(0.0, |my_rank| {
+                                (my_rank - page.offset) as f32 - (scroll_bar.max_items / 2) as f32
+                            });
+                        }
+                        match page.entries.len() {
+                            0 => "No records".to_string(),
+                            len => format!(
+                                "{}-{} / {}",
+                                page.offset + 1,
+                                page.offset + len,
+                                page.total
+                            ),
                         }
                     }
-                    Err(error) => info!("{:?}", error),
+                    Err(NetworkError::NoEntry) => "Not ranked yet".to_string(),
+                    Err(error) => {
+                        info!("{:?}", error);
+                        String::new()
+                    }
+                };
+                for (mut text, _) in text_query
+                    .iter_mut()
+                    .filter(|(_, text_type)| **text_type == MyTextType::RankingPage)
+                {
+                    text.sections[0].value = page_text.clone();
                 }
                 player_state.pop().unwrap();
             }
-            ResponseType::ClearRanking(_) => todo!(),
         }
     }
 }
+
+/// "rank. value | name", with `*` after the name if undo or a hint was used
+fn ranking_entry_to_string(kind: &RankingKind, entry: &RankingEntry) -> String {
+    let time = || duration_to_string(Duration::from_micros(entry.time_micros as u64));
+    let value = match kind {
+        RankingKind::DailyTime(_) => time(),
+        RankingKind::DailyMove(_) => entry.moves.to_string(),
+        RankingKind::DailyComposite(_) => format!("{} / {}", entry.moves, time()),
+        RankingKind::AllTime(key) | RankingKind::Weekly(key) => match key.mode {
+            LeaderboardMode::TimeAttack => time(),
+            LeaderboardMode::MinimalMovement => entry.moves.to_string(),
+        },
+    };
+    format!(
+        "{}. {} | {}{}",
+        entry.rank,
+        value,
+        entry.name,
+        if entry.assisted { "*" } else { "" }
+    )
+}
@@ -0,0 +1,1301 @@
+// persistence layer for a server implementing the protocol in lib.rs
+
+use std::time::Duration;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use rusqlite::{
+    params, params_from_iter,
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    Connection, OptionalExtension, Row, ToSql, Transaction,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    verify_score, BoardString, DailyRanking, LeaderboardKey, LeaderboardMode, Move, NetworkError,
+    PlayerToken, RankingEntry, RankingKind, RankingPage, MAX_RANKING_PAGE_SIZE,
+};
+
+/// Schema migrations, applied in order.
+/// `PRAGMA user_version` holds the number of applied migrations.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "CREATE TABLE puzzle_state (
+        url_key TEXT PRIMARY KEY,
+        board_string TEXT NOT NULL
+    );
+    CREATE TABLE daily_puzzle (
+        date TEXT PRIMARY KEY,
+        board_string TEXT NOT NULL
+    );
+    CREATE TABLE daily_score (
+        date TEXT NOT NULL REFERENCES daily_puzzle(date),
+        name TEXT NOT NULL,
+        time_micros INTEGER NOT NULL,
+        moves INTEGER NOT NULL,
+        PRIMARY KEY (date, name)
+    );",
+    // 2: player identity
+    "CREATE TABLE player (
+        id TEXT PRIMARY KEY,
+        secret TEXT NOT NULL,
+        name TEXT NOT NULL UNIQUE
+    );
+    ALTER TABLE daily_score ADD COLUMN player_id TEXT REFERENCES player(id);
+    CREATE UNIQUE INDEX daily_score_player ON daily_score(date, player_id);",
+    // 3: leaderboards of competitive modes
+    "CREATE TABLE leaderboard_score (
+        mode TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        easy_mode INTEGER NOT NULL,
+        player_id TEXT NOT NULL REFERENCES player(id),
+        date TEXT NOT NULL,
+        time_micros INTEGER NOT NULL,
+        moves INTEGER NOT NULL
+    );
+    CREATE INDEX leaderboard_score_key ON leaderboard_score(mode, size, easy_mode, date);",
+    // 4: submission time and undo usage of scores
+    "ALTER TABLE daily_score ADD COLUMN submitted_at TEXT;
+    ALTER TABLE daily_score ADD COLUMN assisted INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE leaderboard_score ADD COLUMN submitted_at TEXT;",
+    // 5: boards issued for leaderboard scores, ids of closed attempts are never reused
+    "CREATE TABLE leaderboard_attempt (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        mode TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        easy_mode INTEGER NOT NULL,
+        player_id TEXT NOT NULL,
+        board_string TEXT NOT NULL,
+        issued_at TEXT NOT NULL
+    );
+    CREATE INDEX leaderboard_attempt_player ON leaderboard_attempt(player_id, mode, size, easy_mode);",
+    // 6: undo or hint usage of leaderboard scores
+    "ALTER TABLE leaderboard_score ADD COLUMN assisted INTEGER NOT NULL DEFAULT 0;",
+];
+
+/// Same range as the board size setting of the client
+const LEADERBOARD_SIZES: std::ops::RangeInclusive<usize> = 2..=8;
+const LEADERBOARD_SHUFFLE_NUMBER_PER_CELL: usize = 100;
+
+#[derive(Debug)]
+pub enum StorageError {
+    Network(NetworkError),
+    Database(rusqlite::Error),
+    Serialization(serde_json::Error),
+    /// Database or `RawData` of a newer schema than this storage, holds its version
+    SchemaVersion(usize),
+}
+
+impl From<NetworkError> for StorageError {
+    fn from(error: NetworkError) -> Self {
+        Self::Network(error)
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Serialization(error)
+    }
+}
+
+/// Every row of every table, used for backup and restore.
+/// Open leaderboard attempts are not kept.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct RawData {
+    pub schema_version: usize,
+    pub puzzle_states: Vec<(String, BoardString)>,
+    pub daily_puzzles: Vec<(NaiveDate, BoardString)>,
+    pub daily_scores: Vec<RawDailyScore>,
+    #[serde(default)]
+    pub players: Vec<RawPlayer>,
+    #[serde(default)]
+    pub leaderboard_scores: Vec<RawLeaderboardScore>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RawDailyScore {
+    pub date: NaiveDate,
+    pub name: String,
+    pub time_micros: i64,
+    pub moves: usize,
+    #[serde(default)]
+    pub player_id: Option<String>,
+    #[serde(default)]
+    pub submitted_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub assisted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RawPlayer {
+    pub token: PlayerToken,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RawLeaderboardScore {
+    pub key: LeaderboardKey,
+    pub player_id: String,
+    pub date: NaiveDate,
+    pub time_micros: i64,
+    pub moves: usize,
+    #[serde(default)]
+    pub submitted_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub assisted: bool,
+}
+
+impl ToSql for LeaderboardMode {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(match self {
+            LeaderboardMode::TimeAttack => "time_attack",
+            LeaderboardMode::MinimalMovement => "minimal_movement",
+        }
+        .into())
+    }
+}
+
+impl FromSql for LeaderboardMode {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "time_attack" => Ok(LeaderboardMode::TimeAttack),
+            "minimal_movement" => Ok(LeaderboardMode::MinimalMovement),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+/// Storage used by a server to answer `RequestType`s.
+/// Protocol level failures are reported as `StorageError::Network`.
+pub trait Storage {
+    /// `NetworkError::KeyAlreadyExist` if `url_key` is taken by another board
+    fn enroll_puzzle_state(
+        &mut self,
+        url_key: &str,
+        board_string: &BoardString,
+    ) -> Result<(), StorageError>;
+
+    /// `NetworkError::NoEntry` if `url_key` is unknown
+    fn get_puzzle_state(&self, url_key: &str) -> Result<BoardString, StorageError>;
+
+    /// Overwrites the daily puzzle of `date` if it already exists
+    fn insert_daily_puzzle(
+        &mut self,
+        date: NaiveDate,
+        board_string: &BoardString,
+    ) -> Result<(), StorageError>;
+
+    /// Answer of `RequestType::GenerateDailyPuzzle`, the same puzzle clients generate offline
+    fn generate_daily_puzzle(&mut self, date: NaiveDate) -> Result<(), StorageError> {
+        self.insert_daily_puzzle(date, &BoardString::daily_puzzle(date))
+    }
+
+    /// `NetworkError::NoEntry` if there is no daily puzzle of `date`
+    fn get_daily_puzzle(&self, date: NaiveDate) -> Result<BoardString, StorageError>;
+
+    /// (first, last) date of daily puzzles
+    fn get_daily_puzzle_date(&self) -> Result<(NaiveDate, NaiveDate), StorageError>;
+
+    /// Bind `name` to the player of `token`, registering a new player on first use.
+    /// `NetworkError::Unauthorized` if `token.secret` does not match,
+    /// `NetworkError::NameAlreadyExist` if `name` is bound to another player
+    fn bind_player_name(&mut self, token: &PlayerToken, name: &str) -> Result<(), StorageError>;
+
+    /// `NetworkError::NoEntry` if there is no daily puzzle of `date`,
+    /// `NetworkError::InvalidScore` if `move_log` does not solve it in `moves`,
+    /// `NetworkError::AlreadyEnrolled` if the player already has a score of `date`,
+    /// and errors of `bind_player_name`
+    fn enroll_daily_score(
+        &mut self,
+        date: NaiveDate,
+        token: &PlayerToken,
+        name: &str,
+        time: Duration,
+        moves: usize,
+        move_log: &[Move],
+        assisted: bool,
+        submitted_at: NaiveDateTime,
+    ) -> Result<(), StorageError>;
+
+    /// Scores with the same ranking value are tied, see `RankingEntry`
+    fn get_daily_ranking(&self, date: NaiveDate) -> Result<DailyRanking, StorageError>;
+
+    fn clear_ranking(&mut self, date: NaiveDate) -> Result<(), StorageError>;
+
+    /// Shuffled board of `key` issued to the player, leaderboard scores are only taken
+    /// on issued boards. Starting another attempt drops the open one of the same `key`.
+    /// `NetworkError::InvalidScore` if `key.size` is not a board size of the client
+    fn start_leaderboard_attempt(
+        &mut self,
+        key: &LeaderboardKey,
+        player_id: &str,
+        issued_at: NaiveDateTime,
+    ) -> Result<(u64, BoardString), StorageError>;
+
+    /// Every enrolled score is kept, leaderboards take the best score of each player.
+    /// An attempt is closed by its score.
+    /// `NetworkError::InvalidScore` if `attempt` is not open for the player and `key`
+    /// or `move_log` does not solve its board in `moves`, and errors of `bind_player_name`
+    fn enroll_leaderboard_score(
+        &mut self,
+        key: &LeaderboardKey,
+        token: &PlayerToken,
+        name: &str,
+        time: Duration,
+        moves: usize,
+        attempt: u64,
+        move_log: &[Move],
+        assisted: bool,
+        submitted_at: NaiveDateTime,
+    ) -> Result<(), StorageError>;
+
+    /// Entries from `offset`, at most `limit` or `MAX_RANKING_PAGE_SIZE` of them.
+    /// Weekly ranking counts scores from Monday of the week of `today`
+    fn get_ranking_page(
+        &self,
+        kind: &RankingKind,
+        offset: usize,
+        limit: usize,
+        today: NaiveDate,
+    ) -> Result<RankingPage, StorageError>;
+
+    /// Entry of the player with `neighbors` entries above and below it.
+    /// `NetworkError::NoEntry` if the player is not ranked
+    fn get_my_rank(
+        &self,
+        kind: &RankingKind,
+        player_id: &str,
+        neighbors: usize,
+        today: NaiveDate,
+    ) -> Result<RankingPage, StorageError>;
+
+    fn export_raw_data(&self) -> Result<RawData, StorageError>;
+
+    /// Replace every table with `raw_data`.
+    /// Dumps of older schemas are accepted, fields added later take their serde defaults.
+    /// `StorageError::SchemaVersion` if `raw_data` is of a newer schema
+    fn import_raw_data(&mut self, raw_data: &RawData) -> Result<(), StorageError>;
+}
+
+pub struct SqliteStorage {
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &str) -> Result<Self, StorageError> {
+        Self::new(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(connection: Connection) -> Result<Self, StorageError> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+        let mut storage = Self { connection };
+        storage.migrate()?;
+        Ok(storage)
+    }
+
+    pub fn schema_version(&self) -> Result<usize, StorageError> {
+        let version: i64 = self
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        Ok(version as usize)
+    }
+
+    /// Apply every migration newer than the current schema version.
+    /// `StorageError::SchemaVersion` if the database is of a newer schema
+    pub fn migrate(&mut self) -> Result<(), StorageError> {
+        let version = self.schema_version()?;
+        if version > MIGRATIONS.len() {
+            return Err(StorageError::SchemaVersion(version));
+        }
+        let transaction = self.connection.transaction()?;
+        for migration in MIGRATIONS.iter().skip(version) {
+            transaction.execute_batch(migration)?;
+        }
+        transaction.pragma_update(None, "user_version", MIGRATIONS.len() as i64)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn insert_raw_data(transaction: &Transaction, raw_data: &RawData) -> Result<(), StorageError> {
+        for (url_key, board_string) in raw_data.puzzle_states.iter() {
+            transaction.execute(
+                "INSERT INTO puzzle_state (url_key, board_string) VALUES (?1, ?2)",
+                params![url_key, serde_json::to_string(board_string)?],
+            )?;
+        }
+        for (date, board_string) in raw_data.daily_puzzles.iter() {
+            transaction.execute(
+                "INSERT INTO daily_puzzle (date, board_string) VALUES (?1, ?2)",
+                params![date, serde_json::to_string(board_string)?],
+            )?;
+        }
+        for player in raw_data.players.iter() {
+            transaction.execute(
+                "INSERT INTO player (id, secret, name) VALUES (?1, ?2, ?3)",
+                params![player.token.id, player.token.secret, player.name],
+            )?;
+        }
+        for score in raw_data.daily_scores.iter() {
+            transaction.execute(
+                "INSERT INTO daily_score
+                (date, name, time_micros, moves, player_id, submitted_at, assisted)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    score.date,
+                    score.name,
+                    score.time_micros,
+                    score.moves as i64,
+                    score.player_id,
+                    score.submitted_at,
+                    score.assisted
+                ],
+            )?;
+        }
+        for score in raw_data.leaderboard_scores.iter() {
+            transaction.execute(
+                "INSERT INTO leaderboard_score
+                (mode, size, easy_mode, player_id, date, time_micros, moves, submitted_at, assisted)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    score.key.mode,
+                    score.key.size as i64,
+                    score.key.easy_mode,
+                    score.player_id,
+                    score.date,
+                    score.time_micros,
+                    score.moves as i64,
+                    score.submitted_at,
+                    score.assisted
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Entries of `query` from `offset` in ranking order, every entry if `limit` is None
+    fn ranking_entries(
+        &self,
+        query: &RankingQuery,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<RankingEntry>, StorageError> {
+        let n = query.params.len();
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT name, time_micros, moves, submitted_at, assisted FROM ({})
+            ORDER BY {} LIMIT ?{} OFFSET ?{}",
+            query.source,
+            RankingQuery::ORDER,
+            n + 1,
+            n + 2
+        ))?;
+        let (limit, offset_param) = (limit.map_or(-1, |limit| limit as i64), offset as i64);
+        let mut entries = statement
+            .query_map(
+                params_from_iter(query.params(&[&limit, &offset_param])),
+                read_ranking_entry,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(first) = entries.first() {
+            let first_rank = self.count_before(query, first, false)? + 1;
+            assign_ranks(&query.kind, &mut entries, offset, first_rank);
+        }
+        Ok(entries)
+    }
+
+    fn ranking_total(&self, query: &RankingQuery) -> Result<usize, StorageError> {
+        let total: i64 = self.connection.query_row(
+            &format!("SELECT COUNT(*) FROM ({})", query.source),
+            params_from_iter(query.params(&[])),
+            |row| row.get(0),
+        )?;
+        Ok(total as usize)
+    }
+
+    /// Number of entries with a better ranking value than `entry`,
+    /// with `by_order` also the tied ones ordered before it
+    fn count_before(
+        &self,
+        query: &RankingQuery,
+        entry: &RankingEntry,
+        by_order: bool,
+    ) -> Result<usize, StorageError> {
+        let n = query.params.len();
+        let condition = match by_order {
+            false => format!("(first_value, second_value) < (?{}, ?{})", n + 1, n + 2),
+            true => format!(
+                "(first_value, second_value, COALESCE(submitted_at, ''), name)
+                < (?{}, ?{}, COALESCE(?{}, ''), ?{})",
+                n + 1,
+                n + 2,
+                n + 3,
+                n + 4
+            ),
+        };
+        let (first, second) = ranking_value(&query.kind, entry);
+        let values: [&dyn ToSql; 4] = [&first, &second, &entry.submitted_at, &entry.name];
+        let count: i64 = self.connection.query_row(
+            &format!("SELECT COUNT(*) FROM ({}) WHERE {condition}", query.source),
+            params_from_iter(query.params(match by_order {
+                false => &values[..2],
+                true => &values[..],
+            })),
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+}
+
+/// Every entry of a ranking as an SQL query of (name, time_micros, moves, submitted_at,
+/// assisted, player_id, first_value, second_value), values are those of `ranking_value`
+struct RankingQuery {
+    kind: RankingKind,
+    source: String,
+    params: Vec<Box<dyn ToSql>>,
+}
+
+impl RankingQuery {
+    /// ties are ordered by submission, scores submitted before it was recorded come first
+    const ORDER: &str = "first_value, second_value, COALESCE(submitted_at, ''), name";
+
+    /// Weekly ranking counts scores from Monday of the week of `today`
+    fn new(kind: &RankingKind, today: NaiveDate) -> Self {
+        match kind {
+            RankingKind::DailyTime(date)
+            | RankingKind::DailyMove(date)
+            | RankingKind::DailyComposite(date) => {
+                let values = match kind {
+                    RankingKind::DailyTime(_) => "time_micros AS first_value, 0 AS second_value",
+                    RankingKind::DailyMove(_) => "moves AS first_value, 0 AS second_value",
+                    _ => "moves AS first_value, time_micros AS second_value",
+                };
+                Self {
+                    kind: *kind,
+                    source: format!(
+                        "SELECT name, time_micros, moves, submitted_at, assisted, player_id, {values}
+                        FROM daily_score WHERE date = ?1"
+                    ),
+                    params: vec![Box::new(*date)],
+                }
+            }
+            RankingKind::AllTime(key) | RankingKind::Weekly(key) => {
+                let since = match kind {
+                    RankingKind::Weekly(_) => Some(week_start(today)),
+                    _ => None,
+                };
+                let value_column = match key.mode {
+                    LeaderboardMode::TimeAttack => "time_micros",
+                    LeaderboardMode::MinimalMovement => "moves",
+                };
+                Self {
+                    kind: *kind,
+                    // best score of each player, other columns of an aggregate query
+                    // are taken from the row of MIN() in SQLite
+                    source: format!(
+                        "SELECT player.name AS name, score.time_micros AS time_micros,
+                            score.moves AS moves, score.submitted_at AS submitted_at,
+                            score.assisted AS assisted, player.id AS player_id,
+                            MIN(score.{value_column}) AS first_value, 0 AS second_value
+                        FROM leaderboard_score AS score JOIN player ON player.id = score.player_id
+                        WHERE mode = ?1 AND size = ?2 AND easy_mode = ?3
+                            AND (?4 IS NULL OR date >= ?4)
+                        GROUP BY score.player_id"
+                    ),
+                    params: vec![
+                        Box::new(key.mode),
+                        Box::new(key.size as i64),
+                        Box::new(key.easy_mode),
+                        Box::new(since),
+                    ],
+                }
+            }
+        }
+    }
+
+    /// Parameters of `source` followed by `extra`, numbered from `params.len() + 1`
+    fn params<'a>(&'a self, extra: &'a [&'a dyn ToSql]) -> impl Iterator<Item = &'a dyn ToSql> {
+        self.params
+            .iter()
+            .map(|param| param.as_ref())
+            .chain(extra.iter().copied())
+    }
+}
+
+/// (name, time_micros, moves, submitted_at, assisted) into unranked entry
+fn read_ranking_entry(row: &Row) -> rusqlite::Result<RankingEntry> {
+    Ok(RankingEntry {
+        rank: 0,
+        name: row.get(0)?,
+        time_micros: row.get(1)?,
+        moves: row.get::<_, i64>(2)? as usize,
+        submitted_at: row.get(3)?,
+        assisted: row.get(4)?,
+    })
+}
+
+/// Entries with the same ranking value are tied
+fn ranking_value(kind: &RankingKind, entry: &RankingEntry) -> (i64, i64) {
+    match kind {
+        RankingKind::DailyTime(_) => (entry.time_micros, 0),
+        RankingKind::DailyMove(_) => (entry.moves as i64, 0),
+        RankingKind::DailyComposite(_) => (entry.moves as i64, entry.time_micros),
+        RankingKind::AllTime(key) | RankingKind::Weekly(key) => match key.mode {
+            LeaderboardMode::TimeAttack => (entry.time_micros, 0),
+            LeaderboardMode::MinimalMovement => (entry.moves as i64, 0),
+        },
+    }
+}
+
+/// Competition ranking of sorted entries from `offset`, entries with the same ranking value
+/// share a rank. `first_rank` is the rank of the first entry, which may be tied with earlier ones
+fn assign_ranks(
+    kind: &RankingKind,
+    entries: &mut [RankingEntry],
+    offset: usize,
+    first_rank: usize,
+) {
+    for i in 0..entries.len() {
+        entries[i].rank = match i {
+            0 => first_rank,
+            _ if ranking_value(kind, &entries[i]) == ranking_value(kind, &entries[i - 1]) => {
+                entries[i - 1].rank
+            }
+            _ => offset + i + 1,
+        };
+    }
+}
+
+/// Monday of the week of `today`
+fn week_start(today: NaiveDate) -> NaiveDate {
+    today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64)
+}
+
+fn parse_board_string(value: String) -> Result<BoardString, StorageError> {
+    Ok(serde_json::from_str(&value)?)
+}
+
+/// `Storage::bind_player_name` on `connection`, also used inside the transaction of an enroll
+fn bind_player(
+    connection: &Connection,
+    token: &PlayerToken,
+    name: &str,
+) -> Result<(), StorageError> {
+    let secret: Option<String> = connection
+        .query_row(
+            "SELECT secret FROM player WHERE id = ?1",
+            params![token.id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if secret
+        .as_ref()
+        .map_or(false, |secret| *secret != token.secret)
+    {
+        return Err(NetworkError::Unauthorized.into());
+    }
+
+    let owner: Option<String> = connection
+        .query_row(
+            "SELECT id FROM player WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if owner.as_ref().map_or(false, |owner| *owner != token.id) {
+        return Err(NetworkError::NameAlreadyExist.into());
+    }
+
+    connection.execute(
+        "INSERT INTO player (id, secret, name) VALUES (?1, ?2, ?3)
+        ON CONFLICT(id) DO UPDATE SET name = excluded.name",
+        params![token.id, token.secret, name],
+    )?;
+    Ok(())
+}
+
+impl Storage for SqliteStorage {
+    fn enroll_puzzle_state(
+        &mut self,
+        url_key: &str,
+        board_string: &BoardString,
+    ) -> Result<(), StorageError> {
+        match self.get_puzzle_state(url_key) {
+            Ok(stored) if stored == *board_string => Ok(()),
+            Ok(_) => Err(NetworkError::KeyAlreadyExist.into()),
+            Err(StorageError::Network(NetworkError::NoEntry)) => {
+                self.connection.execute(
+                    "INSERT INTO puzzle_state (url_key, board_string) VALUES (?1, ?2)",
+                    params![url_key, serde_json::to_string(board_string)?],
+                )?;
+                Ok(())
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn get_puzzle_state(&self, url_key: &str) -> Result<BoardString, StorageError> {
+        self.connection
+            .query_row(
+                "SELECT board_string FROM puzzle_state WHERE url_key = ?1",
+                params![url_key],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+            .map_or(Err(NetworkError::NoEntry.into()), parse_board_string)
+    }
+
+    fn insert_daily_puzzle(
+        &mut self,
+        date: NaiveDate,
+        board_string: &BoardString,
+    ) -> Result<(), StorageError> {
+        self.connection.execute(
+            "INSERT INTO daily_puzzle (date, board_string) VALUES (?1, ?2)
+            ON CONFLICT(date) DO UPDATE SET board_string = excluded.board_string",
+            params![date, serde_json::to_string(board_string)?],
+        )?;
+        Ok(())
+    }
+
+    fn get_daily_puzzle(&self, date: NaiveDate) -> Result<BoardString, StorageError> {
+        self.connection
+            .query_row(
+                "SELECT board_string FROM daily_puzzle WHERE date = ?1",
+                params![date],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+            .map_or(Err(NetworkError::NoEntry.into()), parse_board_string)
+    }
+
+    fn get_daily_puzzle_date(&self) -> Result<(NaiveDate, NaiveDate), StorageError> {
+        let (first, last): (Option<NaiveDate>, Option<NaiveDate>) = self.connection.query_row(
+            "SELECT MIN(date), MAX(date) FROM daily_puzzle",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        match (first, last) {
+            (Some(first), Some(last)) => Ok((first, last)),
+            _ => Err(NetworkError::NoEntry.into()),
+        }
+    }
+
+    fn bind_player_name(&mut self, token: &PlayerToken, name: &str) -> Result<(), StorageError> {
+        bind_player(&self.connection, token, name)
+    }
+
+    fn enroll_daily_score(
+        &mut self,
+        date: NaiveDate,
+        token: &PlayerToken,
+        name: &str,
+        time: Duration,
+        moves: usize,
+        move_log: &[Move],
+        assisted: bool,
+        submitted_at: NaiveDateTime,
+    ) -> Result<(), StorageError> {
+        verify_score(&self.get_daily_puzzle(date)?, move_log, moves, false)?;
+        // a rejected score must not rename the player
+        let transaction = self.connection.transaction()?;
+        let exists = transaction
+            .query_row(
+                "SELECT 1 FROM daily_score WHERE date = ?1 AND (player_id = ?2 OR name = ?3)",
+                params![date, token.id, name],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if exists {
+            return Err(NetworkError::AlreadyEnrolled.into());
+        }
+        bind_player(&transaction, token, name)?;
+        transaction.execute(
+            "INSERT INTO daily_score
+            (date, name, time_micros, moves, player_id, submitted_at, assisted)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                date,
+                name,
+                time.as_micros() as i64,
+                moves as i64,
+                token.id,
+                submitted_at,
+                assisted
+            ],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn get_daily_ranking(&self, date: NaiveDate) -> Result<DailyRanking, StorageError> {
+        let entries =
+            |kind: RankingKind| self.ranking_entries(&RankingQuery::new(&kind, date), 0, None);
+        Ok(DailyRanking {
+            date,
+            time_ranking: entries(RankingKind::DailyTime(date))?,
+            move_ranking: entries(RankingKind::DailyMove(date))?,
+            composite_ranking: entries(RankingKind::DailyComposite(date))?,
+        })
+    }
+
+    fn clear_ranking(&mut self, date: NaiveDate) -> Result<(), StorageError> {
+        self.connection
+            .execute("DELETE FROM daily_score WHERE date = ?1", params![date])?;
+        Ok(())
+    }
+
+    fn start_leaderboard_attempt(
+        &mut self,
+        key: &LeaderboardKey,
+        player_id: &str,
+        issued_at: NaiveDateTime,
+    ) -> Result<(u64, BoardString), StorageError> {
+        if !LEADERBOARD_SIZES.contains(&key.size) {
+            return Err(NetworkError::InvalidScore.into());
+        }
+        let board_string = loop {
+            let board_string = BoardString::shuffled(
+                key.size,
+                rand::random(),
+                LEADERBOARD_SHUFFLE_NUMBER_PER_CELL * key.size * key.size,
+            );
+            if !board_string.is_solved(key.easy_mode) {
+                break board_string;
+            }
+        };
+
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "DELETE FROM leaderboard_attempt
+            WHERE player_id = ?1 AND mode = ?2 AND size = ?3 AND easy_mode = ?4",
+            params![player_id, key.mode, key.size as i64, key.easy_mode],
+        )?;
+        transaction.execute(
+            "INSERT INTO leaderboard_attempt
+            (mode, size, easy_mode, player_id, board_string, issued_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                key.mode,
+                key.size as i64,
+                key.easy_mode,
+                player_id,
+                serde_json::to_string(&board_string)?,
+                issued_at
+            ],
+        )?;
+        let attempt = transaction.last_insert_rowid() as u64;
+        transaction.commit()?;
+        Ok((attempt, board_string))
+    }
+
+    fn enroll_leaderboard_score(
+        &mut self,
+        key: &LeaderboardKey,
+        token: &PlayerToken,
+        name: &str,
+        time: Duration,
+        moves: usize,
+        attempt: u64,
+        move_log: &[Move],
+        assisted: bool,
+        submitted_at: NaiveDateTime,
+    ) -> Result<(), StorageError> {
+        let transaction = self.connection.transaction()?;
+        let initial_board = transaction
+            .query_row(
+                "SELECT board_string FROM leaderboard_attempt
+                WHERE id = ?1 AND mode = ?2 AND size = ?3 AND easy_mode = ?4 AND player_id = ?5",
+                params![
+                    attempt as i64,
+                    key.mode,
+                    key.size as i64,
+                    key.easy_mode,
+                    token.id
+                ],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+            .map_or(Err(NetworkError::InvalidScore.into()), parse_board_string)?;
+        verify_score(&initial_board, move_log, moves, key.easy_mode)?;
+        bind_player(&transaction, token, name)?;
+        transaction.execute(
+            "DELETE FROM leaderboard_attempt WHERE id = ?1",
+            params![attempt as i64],
+        )?;
+        transaction.execute(
+            "INSERT INTO leaderboard_score
+            (mode, size, easy_mode, player_id, date, time_micros, moves, submitted_at, assisted)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                key.mode,
+                key.size as i64,
+                key.easy_mode,
+                token.id,
+                submitted_at.date(),
+                time.as_micros() as i64,
+                moves as i64,
+                submitted_at,
+                assisted
+            ],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn get_ranking_page(
+        &self,
+        kind: &RankingKind,
+        offset: usize,
+        limit: usize,
+        today: NaiveDate,
+    ) -> Result<RankingPage, StorageError> {
+        let query = RankingQuery::new(kind, today);
+        Ok(RankingPage {
+            kind: *kind,
+            offset,
+            total: self.ranking_total(&query)?,
+            entries: self.ranking_entries(
+                &query,
+                offset,
+                Some(limit.min(MAX_RANKING_PAGE_SIZE)),
+            )?,
+            my_rank: None,
+        })
+    }
+
+    fn get_my_rank(
+        &self,
+        kind: &RankingKind,
+        player_id: &str,
+        neighbors: usize,
+        today: NaiveDate,
+    ) -> Result<RankingPage, StorageError> {
+        let query = RankingQuery::new(kind, today);
+        let my_entry = self
+            .connection
+            .query_row(
+                &format!(
+                    "SELECT name, time_micros, moves, submitted_at, assisted FROM ({})
+                    WHERE player_id = ?{}",
+                    query.source,
+                    query.params.len() + 1
+                ),
+                params_from_iter(query.params(&[&player_id])),
+                read_ranking_entry,
+            )
+            .optional()?
+            .ok_or(NetworkError::NoEntry)?;
+        let my_rank = self.count_before(&query, &my_entry, true)?;
+        let offset = my_rank.saturating_sub(neighbors);
+        let limit = (my_rank - offset + neighbors + 1).min(MAX_RANKING_PAGE_SIZE);
+        Ok(RankingPage {
+            kind: *kind,
+            offset,
+            total: self.ranking_total(&query)?,
+            entries: self.ranking_entries(&query, offset, Some(limit))?,
+            my_rank: Some(my_rank),
+        })
+    }
+
+    fn export_raw_data(&self) -> Result<RawData, StorageError> {
+        let mut raw_data = RawData {
+            schema_version: self.schema_version()?,
+            ..Default::default()
+        };
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT url_key, board_string FROM puzzle_state ORDER BY url_key")?;
+        for row in statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (url_key, board_string): (String, String) = row?;
+            raw_data
+                .puzzle_states
+                .push((url_key, parse_board_string(board_string)?));
+        }
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT date, board_string FROM daily_puzzle ORDER BY date")?;
+        for row in statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (date, board_string): (NaiveDate, String) = row?;
+            raw_data
+                .daily_puzzles
+                .push((date, parse_board_string(board_string)?));
+        }
+
+        let mut statement = self.connection.prepare(
+            "SELECT date, name, time_micros, moves, player_id, submitted_at, assisted
+            FROM daily_score ORDER BY date, name",
+        )?;
+        raw_data.daily_scores = statement
+            .query_map([], |row| {
+                Ok(RawDailyScore {
+                    date: row.get(0)?,
+                    name: row.get(1)?,
+                    time_micros: row.get(2)?,
+                    moves: row.get::<_, i64>(3)? as usize,
+                    player_id: row.get(4)?,
+                    submitted_at: row.get(5)?,
+                    assisted: row.get(6)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT id, secret, name FROM player ORDER BY id")?;
+        raw_data.players = statement
+            .query_map([], |row| {
+                Ok(RawPlayer {
+                    token: PlayerToken {
+                        id: row.get(0)?,
+                        secret: row.get(1)?,
+                    },
+                    name: row.get(2)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+
+        let mut statement = self.connection.prepare(
+            "SELECT mode, size, easy_mode, player_id, date, time_micros, moves, submitted_at,
+                assisted
+            FROM leaderboard_score
+            ORDER BY mode, size, easy_mode, date, submitted_at, player_id",
+        )?;
+        raw_data.leaderboard_scores = statement
+            .query_map([], |row| {
+                Ok(RawLeaderboardScore {
+                    key: LeaderboardKey {
+                        mode: row.get(0)?,
+                        size: row.get::<_, i64>(1)? as usize,
+                        easy_mode: row.get(2)?,
+                    },
+                    player_id: row.get(3)?,
+                    date: row.get(4)?,
+                    time_micros: row.get(5)?,
+                    moves: row.get::<_, i64>(6)? as usize,
+                    submitted_at: row.get(7)?,
+                    assisted: row.get(8)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+
+        Ok(raw_data)
+    }
+
+    fn import_raw_data(&mut self, raw_data: &RawData) -> Result<(), StorageError> {
+        if raw_data.schema_version > MIGRATIONS.len() {
+            return Err(StorageError::SchemaVersion(raw_data.schema_version));
+        }
+        let transaction = self.connection.transaction()?;
+        transaction.execute_batch(
+            "DELETE FROM leaderboard_attempt;
+            DELETE FROM leaderboard_score;
+            DELETE FROM daily_score;
+            DELETE FROM player;
+            DELETE FROM daily_puzzle;
+            DELETE FROM puzzle_state;",
+        )?;
+        Self::insert_raw_data(&transaction, raw_data)?;
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 1, 5)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn token(id: &str) -> PlayerToken {
+        PlayerToken {
+            id: id.to_string(),
+            secret: format!("{id}-secret"),
+        }
+    }
+
+    const KEY: LeaderboardKey = LeaderboardKey {
+        mode: LeaderboardMode::TimeAttack,
+        size: 3,
+        easy_mode: false,
+    };
+
+    /// attempt of `player_id` whose board is solved by `[Left, Up]`
+    fn start_attempt(storage: &mut SqliteStorage, player_id: &str) -> u64 {
+        let (attempt, board_string) = storage
+            .start_leaderboard_attempt(&KEY, player_id, at(9))
+            .unwrap();
+        assert!(!board_string.is_solved(KEY.easy_mode));
+        let mut board_string = BoardString::solved(3);
+        board_string.apply_move(Move::Down).unwrap();
+        board_string.apply_move(Move::Right).unwrap();
+        storage
+            .connection
+            .execute(
+                "UPDATE leaderboard_attempt SET board_string = ?1 WHERE id = ?2",
+                params![
+                    serde_json::to_string(&board_string).unwrap(),
+                    attempt as i64
+                ],
+            )
+            .unwrap();
+        attempt
+    }
+
+    fn enroll(
+        storage: &mut SqliteStorage,
+        key: &LeaderboardKey,
+        player_id: &str,
+        attempt: u64,
+    ) -> Result<(), StorageError> {
+        storage.enroll_leaderboard_score(
+            key,
+            &token(player_id),
+            player_id,
+            Duration::from_secs(10),
+            2,
+            attempt,
+            &[Move::Left, Move::Up],
+            false,
+            at(10),
+        )
+    }
+
+    #[test]
+    fn leaderboard_score_needs_an_issued_attempt() {
+        let mut storage = SqliteStorage::open_in_memory().unwrap();
+        let attempt = start_attempt(&mut storage, "alice");
+        assert!(matches!(
+            enroll(&mut storage, &KEY, "bob", attempt),
+            Err(StorageError::Network(NetworkError::InvalidScore))
+        ));
+        let other_key = LeaderboardKey {
+            easy_mode: true,
+            ..KEY
+        };
+        assert!(matches!(
+            enroll(&mut storage, &other_key, "alice", attempt),
+            Err(StorageError::Network(NetworkError::InvalidScore))
+        ));
+        assert!(enroll(&mut storage, &KEY, "alice", attempt).is_ok());
+        // closed by its score
+        assert!(matches!(
+            enroll(&mut storage, &KEY, "alice", attempt),
+            Err(StorageError::Network(NetworkError::InvalidScore))
+        ));
+    }
+
+    #[test]
+    fn new_attempt_drops_the_open_one() {
+        let mut storage = SqliteStorage::open_in_memory().unwrap();
+        let first = start_attempt(&mut storage, "alice");
+        let second = start_attempt(&mut storage, "alice");
+        assert!(matches!(
+            enroll(&mut storage, &KEY, "alice", first),
+            Err(StorageError::Network(NetworkError::InvalidScore))
+        ));
+        assert!(enroll(&mut storage, &KEY, "alice", second).is_ok());
+    }
+
+    /// daily scores of (name, time seconds, moves), submitted an hour apart
+    fn daily_storage(scores: &[(&str, u64, usize)]) -> SqliteStorage {
+        let mut storage = SqliteStorage::open_in_memory().unwrap();
+        let date = at(0).date();
+        storage
+            .insert_daily_puzzle(date, &BoardString::daily_puzzle(date))
+            .unwrap();
+        for (hour, (name, time, moves)) in scores.iter().enumerate() {
+            storage.bind_player_name(&token(name), name).unwrap();
+            storage
+                .connection
+                .execute(
+                    "INSERT INTO daily_score
+                    (date, name, time_micros, moves, player_id, submitted_at)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        date,
+                        name,
+                        Duration::from_secs(*time).as_micros() as i64,
+                        *moves as i64,
+                        name,
+                        at(hour as u32)
+                    ],
+                )
+                .unwrap();
+        }
+        storage
+    }
+
+    fn names(page: &RankingPage) -> Vec<(usize, &str)> {
+        page.entries
+            .iter()
+            .map(|entry| (entry.rank, entry.name.as_str()))
+            .collect()
+    }
+
+    fn entry(name: &str, time: i64) -> RankingEntry {
+        RankingEntry {
+            rank: 0,
+            name: name.to_string(),
+            time_micros: time,
+            moves: 0,
+            submitted_at: None,
+            assisted: false,
+        }
+    }
+
+    #[test]
+    fn tied_entries_share_a_rank() {
+        let kind = RankingKind::DailyTime(at(0).date());
+        let mut entries = [
+            entry("a", 10),
+            entry("b", 20),
+            entry("c", 20),
+            entry("d", 30),
+            entry("e", 30),
+            entry("f", 40),
+        ];
+        assign_ranks(&kind, &mut entries, 0, 1);
+        let ranks: Vec<_> = entries.iter().map(|entry| entry.rank).collect();
+        assert_eq!(ranks, [1, 2, 2, 4, 4, 6]);
+
+        // page starting in the middle of a tie
+        let mut entries = [entry("c", 20), entry("d", 30)];
+        assign_ranks(&kind, &mut entries, 2, 2);
+        let ranks: Vec<_> = entries.iter().map(|entry| entry.rank).collect();
+        assert_eq!(ranks, [2, 4]);
+    }
+
+    #[test]
+    fn ranking_page_keeps_ties_across_pages() {
+        let storage = daily_storage(&[
+            ("alice", 30, 40),
+            ("bob", 20, 50),
+            ("carol", 20, 30),
+            ("dave", 10, 60),
+        ]);
+        let kind = RankingKind::DailyTime(at(0).date());
+        let page = storage
+            .get_ranking_page(&kind, 0, 10, at(0).date())
+            .unwrap();
+        assert_eq!(page.total, 4);
+        assert_eq!(
+            names(&page),
+            [(1, "dave"), (2, "bob"), (2, "carol"), (4, "alice")]
+        );
+        let page = storage.get_ranking_page(&kind, 2, 1, at(0).date()).unwrap();
+        assert_eq!((page.offset, page.total), (2, 4));
+        assert_eq!(names(&page), [(2, "carol")]);
+
+        let kind = RankingKind::DailyComposite(at(0).date());
+        let page = storage
+            .get_ranking_page(&kind, 1, 10, at(0).date())
+            .unwrap();
+        assert_eq!(names(&page), [(2, "alice"), (3, "bob"), (4, "dave")]);
+    }
+
+    #[test]
+    fn my_rank_is_the_index_in_ranking_order() {
+        let storage = daily_storage(&[
+            ("alice", 20, 40),
+            ("bob", 10, 40),
+            ("carol", 30, 40),
+            ("dave", 10, 30),
+        ]);
+        let kind = RankingKind::DailyMove(at(0).date());
+        let page = storage.get_my_rank(&kind, "bob", 1, at(0).date()).unwrap();
+        // tied by moves, ordered by submission
+        assert_eq!(page.my_rank, Some(2));
+        assert_eq!(page.offset, 1);
+        assert_eq!(names(&page), [(2, "alice"), (2, "bob"), (2, "carol")]);
+        assert!(matches!(
+            storage.get_my_rank(&kind, "erin", 1, at(0).date()),
+            Err(StorageError::Network(NetworkError::NoEntry))
+        ));
+    }
+
+    #[test]
+    fn leaderboard_ranks_best_score_of_each_player() {
+        let mut storage = SqliteStorage::open_in_memory().unwrap();
+        for (player_id, time, date, assisted) in [
+            ("alice", 30, at(0), false),
+            ("alice", 10, at(0) - chrono::Duration::days(7), true),
+            ("bob", 20, at(0), false),
+        ] {
+            storage
+                .bind_player_name(&token(player_id), player_id)
+                .unwrap();
+            storage
+                .connection
+                .execute(
+                    "INSERT INTO leaderboard_score
+                    (mode, size, easy_mode, player_id, date, time_micros, moves, submitted_at,
+                        assisted)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, ?7, ?8)",
+                    params![
+                        KEY.mode,
+                        KEY.size as i64,
+                        KEY.easy_mode,
+                        player_id,
+                        date.date(),
+                        Duration::from_secs(time).as_micros() as i64,
+                        date,
+                        assisted
+                    ],
+                )
+                .unwrap();
+        }
+        let all_time = storage
+            .get_ranking_page(&RankingKind::AllTime(KEY), 0, 10, at(0).date())
+            .unwrap();
+        assert_eq!(names(&all_time), [(1, "alice"), (2, "bob")]);
+        assert_eq!(all_time.entries[0].time_micros, 10_000_000);
+        // marked as the best score was
+        assert!(all_time.entries[0].assisted);
+        let weekly = storage
+            .get_ranking_page(&RankingKind::Weekly(KEY), 0, 10, at(0).date())
+            .unwrap();
+        assert_eq!(names(&weekly), [(1, "bob"), (2, "alice")]);
+        assert!(!weekly.entries[1].assisted);
+    }
+
+    #[test]
+    fn attempt_of_unknown_size_is_rejected() {
+        let mut storage = SqliteStorage::open_in_memory().unwrap();
+        let key = LeaderboardKey { size: 100, ..KEY };
+        assert!(matches!(
+            storage.start_leaderboard_attempt(&key, "alice", at(9)),
+            Err(StorageError::Network(NetworkError::InvalidScore))
+        ));
+    }
+
+    #[test]
+    fn newer_database_is_not_migrated() {
+        let mut storage = SqliteStorage::open_in_memory().unwrap();
+        let newer = MIGRATIONS.len() + 1;
+        storage
+            .connection
+            .pragma_update(None, "user_version", newer as i64)
+            .unwrap();
+        assert!(matches!(
+            storage.migrate(),
+            Err(StorageError::SchemaVersion(version)) if version == newer
+        ));
+        assert_eq!(storage.schema_version().unwrap(), newer);
+    }
+}
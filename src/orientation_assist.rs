@@ -0,0 +1,247 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    math::vec3,
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+
+use crate::{
+    block::BlockMesh,
+    game::{BlockStatus, GameState},
+    player::{reset_play_log, PlayLog, PlayerState},
+    settings::Settings,
+};
+
+const MARKER_SIZE: f32 = 0.3;
+const MARKER_HEIGHT: f32 = 0.52; // above the top face and face indicators
+const ARROW_HEIGHT: f32 = 0.53;
+
+pub struct OrientationAssistPlugin;
+
+impl Plugin for OrientationAssistPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_assist_assets)
+            .add_system(spawn_assist_marks)
+            .add_system(update_assist_marks)
+            .add_system(record_assist_usage)
+            // the play log is reset when shuffled, with the overlay already shown
+            .add_system_set(
+                SystemSet::on_enter(PlayerState::Shuffled)
+                    .with_system(record_assist_usage.after(reset_play_log)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(PlayerState::Solving).with_system(record_assist_usage),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct AssistAssets {
+    marker_mesh: Handle<Mesh>,
+    arrow_mesh: Handle<Mesh>,
+    correct: Handle<StandardMaterial>,
+    misoriented: Handle<StandardMaterial>,
+    misplaced: Handle<StandardMaterial>,
+    arrow: Handle<StandardMaterial>,
+}
+
+/// Status marker of `block`, or the arrow of the roll fixing its orientation
+#[derive(Component)]
+struct AssistMark {
+    block: Entity,
+    is_arrow: bool,
+}
+
+fn setup_assist_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mut material = |color: Color| {
+        materials.add(StandardMaterial {
+            base_color: color,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        })
+    };
+    commands.insert_resource(AssistAssets {
+        marker_mesh: meshes.add(Mesh::from(shape::Quad::new(Vec2::splat(MARKER_SIZE)))),
+        arrow_mesh: meshes.add(arrow_mesh()),
+        correct: material(Color::rgba(0.2, 0.9, 0.3, 0.8)),
+        misoriented: material(Color::rgba(1.0, 0.8, 0.1, 0.8)),
+        misplaced: material(Color::rgba(1.0, 0.2, 0.2, 0.8)),
+        arrow: material(Color::rgba(1.0, 1.0, 1.0, 0.9)),
+    });
+}
+
+/// Flat arrow on the XZ plane pointing +z
+fn arrow_mesh() -> Mesh {
+    let positions = vec![
+        // stem
+        [-0.05, 0.0, -0.3],
+        [0.05, 0.0, -0.3],
+        [0.05, 0.0, 0.05],
+        [-0.05, 0.0, 0.05],
+        // head
+        [-0.15, 0.0, 0.05],
+        [0.15, 0.0, 0.05],
+        [0.0, 0.0, 0.3],
+    ];
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_indices(Some(Indices::U32(vec![0, 2, 1, 0, 3, 2, 4, 6, 5])));
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![[0.0, 1.0, 0.0]; positions.len()],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; positions.len()]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh
+}
+
+fn spawn_assist_marks(
+    mut commands: Commands,
+    block_query: Query<Entity, Added<BlockMesh>>,
+    assist_assets: Res<AssistAssets>,
+) {
+    for block in block_query.iter() {
+        commands.spawn((
+            PbrBundle {
+                mesh: assist_assets.marker_mesh.clone(),
+                material: assist_assets.correct.clone(),
+                visibility: Visibility::INVISIBLE,
+                ..default()
+            },
+            AssistMark {
+                block,
+                is_arrow: false,
+            },
+        ));
+        commands.spawn((
+            PbrBundle {
+                mesh: assist_assets.arrow_mesh.clone(),
+                material: assist_assets.arrow.clone(),
+                visibility: Visibility::INVISIBLE,
+                ..default()
+            },
+            AssistMark {
+                block,
+                is_arrow: true,
+            },
+        ));
+    }
+}
+
+/// World direction of the roll bringing the top face of the goal orientation up,
+/// `None` when it is already up and only turned around
+fn fixing_roll(rotation: Quat) -> Option<Vec3> {
+    // a roll toward +x brings the -x side to the top
+    let top = rotation * Vec3::Y;
+    match top.y {
+        y if y > 0.5 => None,
+        y if y < -0.5 => Some(Vec3::X),
+        _ => Some(-vec3(top.x.round(), 0.0, top.z.round())),
+    }
+}
+
+/// Follows the blocks and shows the status of each with `GameState::block_status`
+fn update_assist_marks(
+    mut commands: Commands,
+    mut mark_query: Query<(
+        Entity,
+        &AssistMark,
+        &mut Transform,
+        &mut Handle<StandardMaterial>,
+        &mut Visibility,
+    )>,
+    block_query: Query<&Transform, (With<BlockMesh>, Without<AssistMark>)>,
+    game_query: Query<&GameState>,
+    assist_assets: Res<AssistAssets>,
+    settings: Res<Settings>,
+) {
+    let game = game_query.single();
+
+    // status of each block at rest
+    let mut statuses = Vec::new();
+    if settings.orientation_assist {
+        for (x, column) in game.board.0.iter().enumerate() {
+            for (z, block) in column.iter().enumerate() {
+                if let Some(block) = block {
+                    if let Ok(transform) = block_query.get(block.entity) {
+                        let status = match block.moving {
+                            Some(_) => None,
+                            None => {
+                                Some(game.block_status(x, z, block, transform, settings.easy_mode))
+                            }
+                        };
+                        statuses.push((block.entity, status));
+                    }
+                }
+            }
+        }
+    }
+
+    for (entity, mark, mut transform, mut material, mut visibility) in mark_query.iter_mut() {
+        let block_transform = match block_query.get(mark.block) {
+            Ok(block_transform) => block_transform,
+            Err(_) => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        let status = statuses
+            .iter()
+            .find(|(block, _)| *block == mark.block)
+            .and_then(|(_, status)| *status);
+        let center = block_transform.translation;
+
+        let is_visible = match (mark.is_arrow, status) {
+            (false, Some(status)) => {
+                let status_material = match status {
+                    BlockStatus::Correct => &assist_assets.correct,
+                    BlockStatus::Misoriented => &assist_assets.misoriented,
+                    BlockStatus::Misplaced => &assist_assets.misplaced,
+                };
+                if *material != *status_material {
+                    *material = status_material.clone();
+                }
+                *transform = Transform::from_translation(vec3(center.x, MARKER_HEIGHT, center.z))
+                    .with_rotation(Quat::from_rotation_x(-FRAC_PI_2));
+                true
+            }
+            (true, Some(BlockStatus::Misoriented)) if settings.assist_arrows => {
+                match fixing_roll(block_transform.rotation) {
+                    Some(direction) => {
+                        *transform =
+                            Transform::from_translation(vec3(center.x, ARROW_HEIGHT, center.z))
+                                .with_rotation(Quat::from_rotation_arc(Vec3::Z, direction));
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        };
+        if visibility.is_visible != is_visible {
+            visibility.is_visible = is_visible;
+        }
+    }
+}
+
+/// Solves with the overlay shown are flagged in statistics
+fn record_assist_usage(
+    settings: Res<Settings>,
+    player_state: Res<State<PlayerState>>,
+    mut play_log: Query<&mut PlayLog>,
+) {
+    if settings.orientation_assist
+        && (*player_state.current() == PlayerState::Shuffled
+            || *player_state.current() == PlayerState::Solving)
+    {
+        let mut play_log = play_log.single_mut();
+        if !play_log.assist_used {
+            play_log.assist_used = true;
+        }
+    }
+}
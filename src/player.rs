@@ -1,7 +1,11 @@
 use bevy::{prelude::*, time::Stopwatch};
 use std::{collections::VecDeque, time::Duration};
 
-use crate::buffered_input::GameInput;
+use crate::{
+    buffered_input::GameInput,
+    local_storage::LocalStorage,
+    network::{Move, PlayerToken},
+};
 
 /// PlayerState represent state shift of player from game start to end
 /// So, PlayerPlugin would control such state transitions of player.
@@ -33,7 +37,11 @@ impl Plugin for PlayerPlugin {
                 SystemSet::on_update(PlayerState::Init).with_system(crate::game::try_url_load),
             )
             .add_system_set(SystemSet::on_enter(PlayerState::Idle).with_system(reset_timer))
-            .add_system_set(SystemSet::on_enter(PlayerState::Shuffled).with_system(reset_timer))
+            .add_system_set(
+                SystemSet::on_enter(PlayerState::Shuffled)
+                    .with_system(reset_timer)
+                    .with_system(reset_play_log),
+            )
             .add_system_set(SystemSet::on_enter(PlayerState::Solving).with_system(start_timer))
             .add_system_set(SystemSet::on_update(PlayerState::Solving).with_system(tick_timer))
             .add_system_set(SystemSet::on_enter(PlayerState::Clear).with_system(stop_timer));
@@ -45,6 +53,7 @@ pub struct PlayLog {
     play_log: VecDeque<GameInput>,
     redo_buffer: VecDeque<GameInput>,
     pub undo_used: bool,
+    pub assist_used: bool,
 }
 
 impl PlayLog {
@@ -68,13 +77,28 @@ impl PlayLog {
         self.play_log.clear();
         self.redo_buffer.clear();
         self.undo_used = false;
+        self.assist_used = false;
     }
 
     pub fn clear_redo_buf(&mut self) {
         self.redo_buffer.clear()
     }
+
+    /// Undo or the orientation assist was used, marked on enrolled scores
+    pub fn assisted(&self) -> bool {
+        self.undo_used || self.assist_used
+    }
+
+    /// Inputs from the start of the puzzle, sent with the score for replay
+    pub fn move_log(&self) -> Vec<Move> {
+        self.play_log.iter().map(|&input| input.into()).collect()
+    }
 }
 
+/// Anonymous identity used for enrolling scores, kept in local storage
+#[derive(Resource)]
+pub struct PlayerIdentity(pub PlayerToken);
+
 #[derive(Component)]
 pub struct PlayerInfo {
     play_timer: Stopwatch,
@@ -122,12 +146,24 @@ fn setup_playinfo(mut commands: Commands) {
     commands
         .spawn(Name::new("PlayLog"))
         .insert(PlayLog::default());
+
+    // generate player identity on first launch
+    let player_token = LocalStorage::get_player_token().unwrap_or_else(|| {
+        let player_token = PlayerToken::generate();
+        LocalStorage::set_player_token(&player_token);
+        player_token
+    });
+    commands.insert_resource(PlayerIdentity(player_token));
 }
 
 fn reset_timer(mut player_info: Query<&mut PlayerInfo>) {
     player_info.single_mut().reset();
 }
 
+pub fn reset_play_log(mut play_log: Query<&mut PlayLog>) {
+    play_log.single_mut().reset();
+}
+
 fn start_timer(mut player_info: Query<&mut PlayerInfo>) {
     player_info.single_mut().start_timer();
 }
@@ -0,0 +1,109 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    buffered_input::KeyBindings,
+    game::{INITIAL_BOARD_SIZE, MAX_BOARD_SIZE, MIN_BOARD_SIZE},
+    local_storage::LocalStorage,
+    theme::Theme,
+    ui::GameMode,
+};
+
+/// User settings, saved to local storage whenever they change
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings {
+    pub move_immediate: bool,
+    pub input_inversion: bool,
+    pub board_size: usize,
+    pub easy_mode: bool,
+    pub skip_how_to_play: HashSet<GameMode>,
+    pub key_bindings: KeyBindings,
+    pub swipe_threshold: f32, // logical pixels of a swipe per cell
+    pub input_buffer_size: usize,
+    pub repeat_filter: f32, // seconds before the same input is accepted again, 0 is off
+    pub interrupt_animation: bool,
+    pub orientation_assist: bool,
+    pub assist_arrows: bool,
+    pub theme: Theme,
+}
+
+pub const MIN_SWIPE_THRESHOLD: f32 = 20.0;
+pub const MAX_SWIPE_THRESHOLD: f32 = 200.0;
+pub const MIN_INPUT_BUFFER_SIZE: usize = 1;
+pub const MAX_INPUT_BUFFER_SIZE: usize = 10;
+pub const MAX_REPEAT_FILTER: f32 = 0.2;
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            move_immediate: false,
+            input_inversion: false,
+            board_size: INITIAL_BOARD_SIZE,
+            easy_mode: false,
+            skip_how_to_play: HashSet::new(),
+            key_bindings: KeyBindings::default(),
+            swipe_threshold: 50.0,
+            input_buffer_size: 3,
+            repeat_filter: 0.03,
+            interrupt_animation: false,
+            orientation_assist: false,
+            assist_arrows: false,
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// Sent after `Settings` changed, with the settings before the change
+pub struct SettingsChangedEvent {
+    pub previous: Settings,
+}
+
+impl Settings {
+    pub fn export(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Missing fields are filled with defaults, out of range values are clamped
+    pub fn import(text: &str) -> Result<Self, serde_json::Error> {
+        let mut settings: Self = serde_json::from_str(text.trim())?;
+        settings.board_size = settings.board_size.clamp(MIN_BOARD_SIZE, MAX_BOARD_SIZE);
+        settings.swipe_threshold = settings
+            .swipe_threshold
+            .clamp(MIN_SWIPE_THRESHOLD, MAX_SWIPE_THRESHOLD);
+        settings.input_buffer_size = settings
+            .input_buffer_size
+            .clamp(MIN_INPUT_BUFFER_SIZE, MAX_INPUT_BUFFER_SIZE);
+        settings.repeat_filter = settings.repeat_filter.clamp(0.0, MAX_REPEAT_FILTER);
+        Ok(settings)
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LocalStorage::get_settings().unwrap_or_default())
+            .add_event::<SettingsChangedEvent>()
+            .add_system(save_settings);
+    }
+}
+
+fn save_settings(
+    settings: Res<Settings>,
+    mut previous: Local<Option<Settings>>,
+    mut settings_changed_event: EventWriter<SettingsChangedEvent>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    match previous.replace(settings.clone()) {
+        Some(previous) if previous != *settings => {
+            LocalStorage::set_settings(&settings);
+            settings_changed_event.send(SettingsChangedEvent { previous });
+        }
+        _ => {}
+    }
+}
@@ -1,118 +1,642 @@
 use bevy::prelude::*;
+use chrono::{Local, NaiveDateTime};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
 use crate::{
+    game::{GameState, INITIAL_BOARD_SIZE},
     local_storage::LocalStorage,
-    player::{PlayerInfo, PlayerState},
+    network::BoardString,
+    player::{PlayLog, PlayerInfo, PlayerState},
+    settings::Settings,
     ui::GameMode,
     utils::duration_to_string,
 };
 
+/// Statistics are separated by mode, board size and difficulty
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct StatisticsConfig {
+    pub mode: GameMode,
+    pub size: usize,
+    pub easy_mode: bool,
+}
+
+impl Default for StatisticsConfig {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::Practice,
+            size: INITIAL_BOARD_SIZE,
+            easy_mode: false,
+        }
+    }
+}
+
+/// Shown instead of a statistic which needs more solves
+const NO_VALUE: &str = "-";
+
+/// Rolling averages of speedcubing, trimmed like WCA averages
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AverageType {
+    Mo3,
+    Ao5,
+    Ao12,
+    Ao50,
+    Ao100,
+}
+
+impl AverageType {
+    pub const ALL: [AverageType; 5] = [
+        AverageType::Mo3,
+        AverageType::Ao5,
+        AverageType::Ao12,
+        AverageType::Ao50,
+        AverageType::Ao100,
+    ];
+
+    pub fn size(&self) -> usize {
+        match self {
+            AverageType::Mo3 => 3,
+            AverageType::Ao5 => 5,
+            AverageType::Ao12 => 12,
+            AverageType::Ao50 => 50,
+            AverageType::Ao100 => 100,
+        }
+    }
+
+    /// Solves dropped from each end, 5% rounded up. Mean of 3 drops nothing.
+    fn trim(&self) -> usize {
+        match self {
+            AverageType::Mo3 => 0,
+            _ => (self.size() * 5 + 99) / 100,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AverageType::Mo3 => "mo3",
+            AverageType::Ao5 => "ao5",
+            AverageType::Ao12 => "ao12",
+            AverageType::Ao50 => "ao50",
+            AverageType::Ao100 => "ao100",
+        }
+    }
+}
+
+/// Mean of `window` without the `trim` best and worst values
+fn trimmed_mean(window: &[f64], trim: usize) -> f64 {
+    let mut sorted = window.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let kept = &sorted[trim..sorted.len() - trim];
+    kept.iter().sum::<f64>() / kept.len() as f64
+}
+
+/// Average of the `average_type.size()` values ending at each value,
+/// None until there are enough values
+fn rolling_averages(values: &[f64], average_type: AverageType) -> Vec<Option<f64>> {
+    let size = average_type.size();
+    (0..values.len())
+        .map(|i| {
+            (i + 1 >= size).then(|| trimmed_mean(&values[i + 1 - size..=i], average_type.trim()))
+        })
+        .collect()
+}
+
+/// Records of the current configuration and session, refreshed whenever they change
+#[derive(Default)]
+struct RecordCache {
+    values: Vec<f64>,
+    timestamps: Vec<Option<NaiveDateTime>>,
+    assisted: Vec<bool>,
+    rolling_averages: [Vec<Option<f64>>; AverageType::ALL.len()], // indexed by `AverageType`
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SolveRecord {
+    pub timestamp: Option<NaiveDateTime>, // None for records migrated from old statistics
+    pub config: StatisticsConfig,
+    pub time: Option<Duration>, // None for minimal movement records migrated from old statistics
+    pub moves: Option<usize>,   // None for time attack records migrated from old statistics
+    pub undo_used: bool,
+    pub initial_board: Option<BoardString>,
+    #[serde(default = "default_session")]
+    pub session: String,
+    #[serde(default)]
+    pub assist_used: bool, // orientation assist shown while solving
+}
+
+/// Session of records made before sessions existed
+const DEFAULT_SESSION: &str = "Default";
+
+fn default_session() -> String {
+    DEFAULT_SESSION.to_string()
+}
+
+/// Commas are removed to keep CSV export valid
+fn sanitize_session(name: &str) -> String {
+    name.replace(',', "").trim().to_string()
+}
+
 #[derive(Serialize, Deserialize, Component)]
+#[serde(from = "StatisticsFormat")]
 pub struct StatisticsManager {
-    mode: GameMode,
-    time_records: Vec<Duration>,
-    move_records: Vec<usize>,
+    #[serde(skip)]
+    config: StatisticsConfig,
+    records: Vec<SolveRecord>,
+    sessions: Vec<String>,
+    session: String, // current session
+    #[serde(skip)]
+    cache: RecordCache,
 }
 
 impl Default for StatisticsManager {
     fn default() -> Self {
         Self {
-            mode: GameMode::Practice,
-            time_records: vec![],
-            move_records: vec![],
+            config: default(),
+            records: vec![],
+            sessions: vec![default_session()],
+            session: default_session(),
+            cache: default(),
         }
     }
 }
 
+#[derive(Debug)]
+pub enum ImportError {
+    Json(serde_json::Error),
+    Csv(usize),  // line number of the invalid row
+    Mode(usize), // number of the record of a mode without statistics
+}
+
+const CSV_HEADER: &str =
+    "timestamp,mode,size,easy_mode,time_micros,moves,undo_used,initial_board,session,assist_used";
+const CSV_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+impl SolveRecord {
+    /// Row of `CSV_HEADER`, initial board is hex of (position, rotation) bytes
+    fn to_csv_row(&self) -> String {
+        format!(
+            "{},{:?},{},{},{},{},{},{},{},{}",
+            self.timestamp.map_or(String::new(), |timestamp| timestamp
+                .format(CSV_TIMESTAMP_FORMAT)
+                .to_string()),
+            self.config.mode,
+            self.config.size,
+            self.config.easy_mode,
+            self.time
+                .map_or(String::new(), |time| time.as_micros().to_string()),
+            self.moves.map_or(String::new(), |moves| moves.to_string()),
+            self.undo_used,
+            self.initial_board
+                .as_ref()
+                .map_or(String::new(), |board| board
+                    .to_arr()
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect()),
+            self.session,
+            self.assist_used,
+        )
+    }
+
+    /// Rows exported before sessions have no session column, and before assist no assist column
+    fn from_csv_row(row: &str) -> Option<Self> {
+        let fields: Vec<_> = row.trim().split(',').collect();
+        let (session, assist_used) = match fields.len() {
+            8 => (DEFAULT_SESSION, "false"),
+            9 => (fields[8], "false"),
+            10 => (fields[8], fields[9]),
+            _ => return None,
+        };
+        let (timestamp, mode, size, easy_mode, time, moves, undo_used, initial_board) =
+            match fields[..8] {
+                [timestamp, mode, size, easy_mode, time, moves, undo_used, initial_board] => (
+                    timestamp,
+                    mode,
+                    size,
+                    easy_mode,
+                    time,
+                    moves,
+                    undo_used,
+                    initial_board,
+                ),
+                _ => return None,
+            };
+        let initial_board = match initial_board {
+            "" => None,
+            hex => {
+                let bytes = (0..hex.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+                    .collect::<Option<Vec<_>>>()?;
+                let pairs = bytes.chunks_exact(2);
+                if !pairs.remainder().is_empty() {
+                    return None;
+                }
+                Some(BoardString(pairs.map(|pair| (pair[0], pair[1])).collect()))
+            }
+        };
+        Some(Self {
+            timestamp: match timestamp {
+                "" => None,
+                timestamp => {
+                    Some(NaiveDateTime::parse_from_str(timestamp, CSV_TIMESTAMP_FORMAT).ok()?)
+                }
+            },
+            config: StatisticsConfig {
+                mode: match mode {
+                    "TimeAttack" => GameMode::TimeAttack,
+                    "MinimalMovement" => GameMode::MinimalMovement,
+                    _ => return None,
+                },
+                size: size.parse().ok()?,
+                easy_mode: easy_mode.parse().ok()?,
+            },
+            time: match time {
+                "" => None,
+                time => Some(Duration::from_micros(time.parse().ok()?)),
+            },
+            moves: match moves {
+                "" => None,
+                moves => Some(moves.parse().ok()?),
+            },
+            undo_used: undo_used.parse().ok()?,
+            initial_board,
+            session: session.to_string(),
+            assist_used: assist_used.parse().ok()?,
+        })
+    }
+}
+
+/// Records of a text exported by `export_csv` or `export_json`
+fn parse_records(text: &str) -> Result<Vec<SolveRecord>, ImportError> {
+    let text = text.trim();
+    match text.starts_with('[') {
+        true => {
+            let records: Vec<SolveRecord> =
+                serde_json::from_str(text).map_err(ImportError::Json)?;
+            // only competitive modes are recorded, like the modes of the CSV rows
+            match records.iter().position(|record| {
+                !matches!(
+                    record.config.mode,
+                    GameMode::TimeAttack | GameMode::MinimalMovement
+                )
+            }) {
+                Some(i) => Err(ImportError::Mode(i + 1)),
+                None => Ok(records),
+            }
+        }
+        false => text
+            .lines()
+            .enumerate()
+            // headers of older exports are prefixes of the current one
+            .filter(|(_, row)| !row.trim().is_empty() && !CSV_HEADER.starts_with(row.trim()))
+            .map(|(i, row)| SolveRecord::from_csv_row(row).ok_or(ImportError::Csv(i + 1)))
+            .collect(),
+    }
+}
+
+/// Stored formats of `StatisticsManager`, old formats are migrated on load
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StatisticsFormat {
+    Records {
+        records: Vec<SolveRecord>,
+        #[serde(default)]
+        sessions: Vec<String>,
+        #[serde(default = "default_session")]
+        session: String,
+    },
+    // only the current mode's records, without board size or difficulty
+    Legacy {
+        time_records: Vec<Duration>,
+        move_records: Vec<usize>,
+    },
+}
+
+impl From<StatisticsFormat> for StatisticsManager {
+    fn from(format: StatisticsFormat) -> Self {
+        let (records, sessions, session) = match format {
+            StatisticsFormat::Records {
+                records,
+                sessions,
+                session,
+            } => (records, sessions, session),
+            StatisticsFormat::Legacy {
+                time_records,
+                move_records,
+            } => {
+                // board size and difficulty were not stored, count them as the default board
+                let legacy_record = |mode, time, moves| SolveRecord {
+                    timestamp: None,
+                    config: StatisticsConfig { mode, ..default() },
+                    time,
+                    moves,
+                    undo_used: false,
+                    initial_board: None,
+                    session: default_session(),
+                    assist_used: false,
+                };
+                let records =
+                    time_records
+                        .into_iter()
+                        .map(|time| legacy_record(GameMode::TimeAttack, Some(time), None))
+                        .chain(move_records.into_iter().map(|moves| {
+                            legacy_record(GameMode::MinimalMovement, None, Some(moves))
+                        }))
+                        .collect();
+                (records, vec![], default_session())
+            }
+        };
+        let mut statistics_manager = Self {
+            config: default(),
+            records,
+            sessions,
+            session,
+            cache: default(),
+        };
+        statistics_manager.collect_sessions();
+        statistics_manager.refresh_cache();
+        statistics_manager
+    }
+}
+
 impl StatisticsManager {
-    pub fn set_mode(&mut self, mode: GameMode) {
-        self.mode = mode;
+    pub fn set_config(&mut self, config: StatisticsConfig) {
+        self.config = config;
+        self.refresh_cache();
     }
 
     fn save_storage(&self) {
         LocalStorage::set_statistics(self);
     }
 
-    pub fn push(&mut self, info: &PlayerInfo) {
-        let (time, move_count) = info.get_player_info();
-        match self.mode {
-            GameMode::TimeAttack => self.time_records.push(time),
-            GameMode::MinimalMovement => self.move_records.push(move_count),
-            _ => unreachable!(),
+    pub fn push(&mut self, record: SolveRecord) {
+        self.records.push(record);
+        self.refresh_cache();
+        self.save_storage();
+    }
+
+    /// Records of the current configuration and session
+    fn records(&self) -> impl Iterator<Item = &SolveRecord> {
+        self.records
+            .iter()
+            .filter(move |record| record.config == self.config && record.session == self.session)
+    }
+
+    fn refresh_cache(&mut self) {
+        let values: Vec<f64> = self
+            .records()
+            .map(|record| self.record_value(record))
+            .collect();
+        self.cache = RecordCache {
+            timestamps: self.records().map(|record| record.timestamp).collect(),
+            assisted: self.records().map(|record| record.assist_used).collect(),
+            rolling_averages: AverageType::ALL
+                .map(|average_type| rolling_averages(&values, average_type)),
+            values,
+        };
+    }
+
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    /// Add sessions of the records and the current session to the session list
+    fn collect_sessions(&mut self) {
+        let sessions = [default_session(), self.session.clone()]
+            .into_iter()
+            .chain(self.records.iter().map(|record| record.session.clone()));
+        for session in sessions {
+            if !self.sessions.contains(&session) {
+                self.sessions.push(session);
+            }
         }
+    }
+
+    /// Move to the next session in the list, or previous one if `offset` is negative
+    pub fn switch_session(&mut self, offset: isize) {
+        let len = self.sessions.len() as isize;
+        let i = self
+            .sessions
+            .iter()
+            .position(|session| *session == self.session);
+        let i = (i.unwrap_or(0) as isize + offset).rem_euclid(len) as usize;
+        self.session = self.sessions[i].clone();
+        self.refresh_cache();
         self.save_storage();
     }
 
-    pub fn delete_statistics(&mut self) {
-        match self.mode {
-            GameMode::TimeAttack => self.time_records.clear(),
-            GameMode::MinimalMovement => self.move_records.clear(),
-            _ => unreachable!(),
+    /// Create a session and switch to it
+    pub fn add_session(&mut self, name: &str) {
+        let name = sanitize_session(name);
+        if name.is_empty() {
+            return;
         }
+        self.session = name;
+        self.collect_sessions();
+        self.refresh_cache();
         self.save_storage();
     }
 
-    pub fn get_record(&self, i: usize) -> String {
-        match self.mode {
-            GameMode::TimeAttack => duration_to_string(self.time_records[i]),
-            GameMode::MinimalMovement => format!("{}", self.move_records[i]),
-            _ => unreachable!(),
+    /// Compared value of each record, moves in minimal movement and microseconds otherwise
+    pub fn values(&self) -> &[f64] {
+        &self.cache.values
+    }
+
+    /// Records of a mode always have its compared value, migrated ones included
+    fn record_value(&self, record: &SolveRecord) -> f64 {
+        match self.config.mode {
+            GameMode::MinimalMovement => record.moves.unwrap_or_default() as f64,
+            _ => record.time.unwrap_or_default().as_micros() as f64,
         }
     }
 
-    pub fn solves(&self) -> usize {
-        match self.mode {
-            GameMode::TimeAttack => self.time_records.len(),
-            GameMode::MinimalMovement => self.move_records.len(),
-            _ => unreachable!(),
+    pub fn timestamps(&self) -> &[Option<NaiveDateTime>] {
+        &self.cache.timestamps
+    }
+
+    pub fn value_to_string(&self, value: f64) -> String {
+        match self.config.mode {
+            GameMode::MinimalMovement => format!("{}", value as usize),
+            _ => duration_to_string(Duration::from_micros(value as u64)),
         }
     }
 
-    pub fn average(&self) -> String {
-        match self.mode {
-            GameMode::TimeAttack => duration_to_string(
-                self.time_records.iter().sum::<Duration>() / self.solves() as u32,
-            ),
-            GameMode::MinimalMovement => {
-                format!(
-                    "{:.2}",
-                    self.move_records.iter().sum::<usize>() as f32 / self.solves() as f32
-                )
+    fn average_to_string(&self, average: f64) -> String {
+        match self.config.mode {
+            GameMode::MinimalMovement => format!("{:.2}", average),
+            _ => duration_to_string(Duration::from_micros(average.round() as u64)),
+        }
+    }
+
+    /// Delete records of the current configuration and session only
+    pub fn delete_statistics(&mut self) {
+        let config = self.config;
+        let session = self.session.clone();
+        self.records
+            .retain(|record| record.config != config || record.session != session);
+        self.refresh_cache();
+        self.save_storage();
+    }
+
+    pub fn get_record(&self, i: usize) -> String {
+        format!(
+            "{}{}",
+            self.value_to_string(self.cache.values[i]),
+            match self.cache.assisted[i] {
+                true => " (assisted)",
+                false => "",
             }
-            _ => unreachable!(),
+        )
+    }
+
+    pub fn solves(&self) -> usize {
+        self.cache.values.len()
+    }
+
+    pub fn average(&self) -> String {
+        let values = self.values();
+        if values.is_empty() {
+            return NO_VALUE.to_string();
         }
+        self.average_to_string(values.iter().sum::<f64>() / values.len() as f64)
     }
 
     pub fn best(&self) -> String {
-        match self.mode {
-            GameMode::TimeAttack => duration_to_string(*self.time_records.iter().min().unwrap()),
-            GameMode::MinimalMovement => format!("{}", self.move_records.iter().min().unwrap()),
-            _ => unreachable!(),
-        }
+        self.values()
+            .iter()
+            .copied()
+            .reduce(f64::min)
+            .map_or(NO_VALUE.to_string(), |value| self.value_to_string(value))
     }
 
     pub fn worst(&self) -> String {
-        match self.mode {
-            GameMode::TimeAttack => duration_to_string(*self.time_records.iter().max().unwrap()),
-            GameMode::MinimalMovement => format!("{}", self.move_records.iter().max().unwrap()),
-            _ => unreachable!(),
+        self.values()
+            .iter()
+            .copied()
+            .reduce(f64::max)
+            .map_or(NO_VALUE.to_string(), |value| self.value_to_string(value))
+    }
+
+    /// Average of the `average_type.size()` solves ending at each solve,
+    /// None until there are enough solves
+    pub fn rolling_averages(&self, average_type: AverageType) -> &[Option<f64>] {
+        &self.cache.rolling_averages[average_type as usize]
+    }
+
+    /// Average of the latest solves
+    pub fn current_average(&self, average_type: AverageType) -> String {
+        self.rolling_averages(average_type)
+            .last()
+            .copied()
+            .flatten()
+            .map_or(NO_VALUE.to_string(), |average| {
+                self.average_to_string(average)
+            })
+    }
+
+    pub fn best_average(&self, average_type: AverageType) -> String {
+        self.rolling_averages(average_type)
+            .iter()
+            .flatten()
+            .copied()
+            .reduce(f64::min)
+            .map_or(NO_VALUE.to_string(), |average| {
+                self.average_to_string(average)
+            })
+    }
+
+    /// Every record of every configuration, one row per solve
+    pub fn export_csv(&self) -> String {
+        let mut csv = CSV_HEADER.to_string();
+        for record in self.records.iter() {
+            csv.push('\n');
+            csv.push_str(&record.to_csv_row());
         }
+        csv
+    }
+
+    pub fn export_json(&self) -> String {
+        serde_json::to_string(&self.records).unwrap()
+    }
+
+    /// Merge records exported by `export_csv` or `export_json`, skipping ones already stored.
+    /// Returns the number of added records.
+    pub fn import(&mut self, text: &str) -> Result<usize, ImportError> {
+        let added = self.merge_records(parse_records(text)?);
+        self.save_storage();
+        Ok(added)
+    }
+
+    /// Add records not stored yet, returns the number of added records
+    fn merge_records(&mut self, records: Vec<SolveRecord>) -> usize {
+        // the same solve may be stored in another session, and records migrated without
+        // timestamp may share a key, so each stored record matches one imported record
+        let key =
+            |record: &SolveRecord| (record.timestamp, record.config, record.time, record.moves);
+        let mut stored: HashMap<_, usize> = HashMap::new();
+        for record in self.records.iter() {
+            *stored.entry(key(record)).or_default() += 1;
+        }
+        let mut added = 0;
+        for mut record in records {
+            let count = stored.entry(key(&record)).or_default();
+            if *count > 0 {
+                *count -= 1;
+            } else {
+                record.session = match sanitize_session(&record.session) {
+                    session if session.is_empty() => default_session(),
+                    session => session,
+                };
+                self.records.push(record);
+                added += 1;
+            }
+        }
+        // keep solve order for rolling averages, records without timestamp are the oldest
+        self.records.sort_by_key(|record| record.timestamp);
+        self.collect_sessions();
+        self.refresh_cache();
+        added
+    }
+
+    /// "4x4 Hard" of the current configuration
+    pub fn config_to_string(&self) -> String {
+        format!(
+            "{}x{} {}",
+            self.config.size,
+            self.config.size,
+            if self.config.easy_mode {
+                "Easy"
+            } else {
+                "Hard"
+            }
+        )
     }
 
     pub fn export(&self) {
         let mut export_string = format!(
-            "Dice 15 Puzzle (dice15puzzle.haje.org)\nStatistics\n\nSolves: {}",
+            "Dice 15 Puzzle (dice15puzzle.haje.org)\nStatistics {} ({})\n\nSolves: {}",
+            self.config_to_string(),
+            self.session,
             self.solves()
         );
         if self.solves() > 0 {
             export_string.push_str(&format!(
-                "\nAverage: {}\nBest: {}\nWorst: {}\nDetails:\n",
+                "\nAverage: {}\nBest: {}\nWorst: {}\n",
                 self.average(),
                 self.best(),
                 self.worst()
             ));
+            for average_type in AverageType::ALL {
+                if self.solves() >= average_type.size() {
+                    export_string.push_str(&format!(
+                        "{}: {} (best {})\n",
+                        average_type.name(),
+                        self.current_average(average_type),
+                        self.best_average(average_type)
+                    ));
+                }
+            }
             for i in 0..self.solves() {
                 export_string.push_str(&format!("{}. {}\n", i + 1, self.get_record(i)));
             }
@@ -129,7 +653,7 @@ impl Plugin for StatisticsManagerPlugin {
     fn build(&self, app: &mut App) {
         app.add_startup_system(spawn_statistics_manager)
             .add_system_set(SystemSet::on_enter(PlayerState::Clear).with_system(on_game_clear))
-            .add_system(set_game_mode);
+            .add_system(set_config);
     }
 }
 
@@ -140,7 +664,10 @@ fn spawn_statistics_manager(mut commands: Commands) {
 fn on_game_clear(
     mut statistics_manager_query: Query<&mut StatisticsManager>,
     player_info_query: Query<&PlayerInfo>,
+    play_log_query: Query<&PlayLog>,
+    game_query: Query<&GameState>,
     game_mode: Res<State<GameMode>>,
+    settings: Res<Settings>,
 ) {
     let mut statistics_manager = statistics_manager_query.single_mut();
 
@@ -150,16 +677,189 @@ fn on_game_clear(
         return;
     }
 
-    statistics_manager.push(player_info_query.single());
+    let game = game_query.single();
+    let (time, moves) = player_info_query.single().get_player_info();
+    let session = statistics_manager.session().to_string();
+    statistics_manager.push(SolveRecord {
+        timestamp: Some(Local::now().naive_local()),
+        config: StatisticsConfig {
+            mode: *game_mode.current(),
+            size: game.size,
+            easy_mode: settings.easy_mode,
+        },
+        time: Some(time),
+        moves: Some(moves),
+        undo_used: play_log_query.single().undo_used,
+        initial_board: game.initial_board.clone(),
+        session,
+        assist_used: play_log_query.single().assist_used,
+    });
 }
 
-fn set_game_mode(
+/// Keyed on the size of the board in play, like the records of `on_game_clear`
+fn set_config(
     mut statistics_manager_query: Query<&mut StatisticsManager>,
+    game_query: Query<&GameState>,
     game_mode: Res<State<GameMode>>,
+    settings: Res<Settings>,
 ) {
-    if game_mode.is_changed() {
-        statistics_manager_query
-            .single_mut()
-            .set_mode(*game_mode.current());
+    let config = StatisticsConfig {
+        mode: *game_mode.current(),
+        size: game_query.single().size,
+        easy_mode: settings.easy_mode,
+    };
+    let mut statistics_manager = statistics_manager_query.single_mut();
+    if statistics_manager.config != config {
+        statistics_manager.set_config(config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn legacy_statistics_are_migrated() {
+        let statistics_manager: StatisticsManager = serde_json::from_str(
+            r#"{"time_records": [{"secs": 12, "nanos": 0}], "move_records": [40, 35]}"#,
+        )
+        .unwrap();
+        let records = &statistics_manager.records;
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].config.mode, GameMode::TimeAttack);
+        assert_eq!(records[0].time, Some(Duration::from_secs(12)));
+        assert_eq!(records[0].moves, None);
+        assert_eq!(records[1].config.mode, GameMode::MinimalMovement);
+        assert_eq!(records[1].time, None);
+        assert_eq!(records[1].moves, Some(40));
+        assert!(records
+            .iter()
+            .all(|record| record.timestamp.is_none() && record.session == DEFAULT_SESSION));
+        assert_eq!(statistics_manager.session(), DEFAULT_SESSION);
+    }
+
+    #[test]
+    fn records_without_sessions_are_migrated() {
+        let statistics_manager: StatisticsManager = serde_json::from_str(
+            r#"{"records": [{
+                "timestamp": null,
+                "config": {"mode": "TimeAttack", "size": 4, "easy_mode": false},
+                "time": {"secs": 12, "nanos": 0},
+                "moves": 50,
+                "undo_used": false,
+                "initial_board": null
+            }]}"#,
+        )
+        .unwrap();
+        let record = &statistics_manager.records[0];
+        assert_eq!(record.time, Some(Duration::from_secs(12)));
+        assert_eq!(record.moves, Some(50));
+        assert_eq!(record.session, DEFAULT_SESSION);
+        assert!(!record.assist_used);
+        assert_eq!(statistics_manager.sessions, vec![default_session()]);
+    }
+
+    #[test]
+    fn trimmed_mean_drops_best_and_worst() {
+        assert_eq!(trimmed_mean(&[3.0, 1.0, 2.0], AverageType::Mo3.trim()), 2.0);
+        assert_eq!(
+            trimmed_mean(&[5.0, 100.0, 4.0, 1.0, 6.0], AverageType::Ao5.trim()),
+            5.0
+        );
+        assert_eq!(AverageType::Ao12.trim(), 1);
+        assert_eq!(AverageType::Ao50.trim(), 3);
+        assert_eq!(AverageType::Ao100.trim(), 5);
+    }
+
+    #[test]
+    fn rolling_averages_start_with_enough_values() {
+        let averages = rolling_averages(&[1.0, 2.0, 3.0, 4.0], AverageType::Mo3);
+        assert_eq!(averages, vec![None, None, Some(2.0), Some(3.0)]);
+    }
+
+    fn record(second: u32, session: &str) -> SolveRecord {
+        SolveRecord {
+            timestamp: NaiveDate::from_ymd_opt(2023, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, second),
+            config: StatisticsConfig {
+                mode: GameMode::TimeAttack,
+                ..default()
+            },
+            time: Some(Duration::from_micros(12_345_678)),
+            moves: Some(50),
+            undo_used: false,
+            initial_board: Some(BoardString(vec![(1, 0), (0, 3)])),
+            session: session.to_string(),
+            assist_used: true,
+        }
+    }
+
+    #[test]
+    fn csv_export_round_trips() {
+        let mut statistics_manager = StatisticsManager::default();
+        statistics_manager.records = vec![record(1, DEFAULT_SESSION), record(2, "Morning")];
+        let records = parse_records(&statistics_manager.export_csv()).unwrap();
+        assert_eq!(records, statistics_manager.records);
+
+        let mut imported = StatisticsManager::default();
+        assert_eq!(imported.merge_records(records.clone()), 2);
+        assert_eq!(
+            imported.sessions,
+            vec![default_session(), "Morning".to_string()]
+        );
+        // the same solves in another session are skipped
+        let moved = records
+            .into_iter()
+            .map(|record| SolveRecord {
+                session: "Evening".to_string(),
+                ..record
+            })
+            .collect();
+        assert_eq!(imported.merge_records(moved), 0);
+    }
+
+    #[test]
+    fn imported_sessions_keep_csv_valid() {
+        let mut statistics_manager = StatisticsManager::default();
+        let json = serde_json::to_string(&[record(1, "a,b"), record(2, ",")]).unwrap();
+        assert_eq!(
+            statistics_manager.merge_records(parse_records(&json).unwrap()),
+            2
+        );
+        assert_eq!(statistics_manager.records[0].session, "ab");
+        assert_eq!(statistics_manager.records[1].session, DEFAULT_SESSION);
+        let records = parse_records(&statistics_manager.export_csv()).unwrap();
+        assert_eq!(records, statistics_manager.records);
+    }
+
+    #[test]
+    fn odd_initial_board_is_rejected() {
+        let row = record(1, DEFAULT_SESSION).to_csv_row();
+        let odd_row = row.replace(",01000003,", ",010000,");
+        assert_ne!(row, odd_row);
+        assert!(SolveRecord::from_csv_row(&odd_row).is_none());
+    }
+
+    #[test]
+    fn records_of_other_modes_are_rejected() {
+        let mut practice = record(1, DEFAULT_SESSION);
+        practice.config.mode = GameMode::Practice;
+        let json = serde_json::to_string(&[record(2, DEFAULT_SESSION), practice]).unwrap();
+        assert!(matches!(parse_records(&json), Err(ImportError::Mode(2))));
+    }
+
+    #[test]
+    fn migrated_records_with_the_same_key_are_kept() {
+        let legacy: StatisticsManager =
+            serde_json::from_str(r#"{"time_records": [], "move_records": [40, 40]}"#).unwrap();
+        let records = parse_records(&legacy.export_json()).unwrap();
+
+        let mut statistics_manager = StatisticsManager::default();
+        assert_eq!(statistics_manager.merge_records(records.clone()), 2);
+        assert_eq!(statistics_manager.merge_records(records), 0);
+        assert_eq!(statistics_manager.records.len(), 2);
     }
 }
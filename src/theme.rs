@@ -0,0 +1,441 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block::BlockMesh,
+    game::GameState,
+    settings::{Settings, SettingsChangedEvent},
+    ui::{MyTextType, BUTTON_HOVER_MUL, BUTTON_PRESS_MUL, BUTTON_WHITE, POPUP_BACKGROUND_COLOR},
+};
+
+/// Colors of block rows
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Palette {
+    #[default]
+    Rainbow,
+    Pastel,
+    Deuteranopia,
+    Protanopia,
+}
+
+/// Okabe-Ito colors, distinguishable with deuteranopia
+const DEUTERANOPIA_COLORS: [Color; 8] = [
+    Color::rgb(0.90, 0.62, 0.0),
+    Color::rgb(0.34, 0.71, 0.91),
+    Color::rgb(0.0, 0.62, 0.45),
+    Color::rgb(0.94, 0.89, 0.26),
+    Color::rgb(0.0, 0.45, 0.70),
+    Color::rgb(0.84, 0.37, 0.0),
+    Color::rgb(0.80, 0.47, 0.65),
+    Color::rgb(0.6, 0.6, 0.6),
+];
+
+/// Ends of the blue to yellow ramp, a hue axis kept with protanopia
+const PROTANOPIA_FIRST: Vec3 = Vec3::new(0.15, 0.35, 0.85);
+const PROTANOPIA_LAST: Vec3 = Vec3::new(1.0, 0.9, 0.3);
+
+impl Palette {
+    pub const ALL: [Palette; 4] = [
+        Palette::Rainbow,
+        Palette::Pastel,
+        Palette::Deuteranopia,
+        Palette::Protanopia,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Palette::Rainbow => "Rainbow",
+            Palette::Pastel => "Pastel",
+            Palette::Deuteranopia => "Deuteranopia",
+            Palette::Protanopia => "Protanopia",
+        }
+    }
+
+    /// Color of the blocks whose goal is in `row` of a `size` board
+    pub fn block_color(&self, row: usize, size: usize) -> Color {
+        match self {
+            Palette::Rainbow => Color::hsl(360.0 * row as f32 / size as f32, 1.0, 0.6),
+            Palette::Pastel => Color::hsl(360.0 * row as f32 / size as f32, 0.7, 0.8),
+            Palette::Deuteranopia => DEUTERANOPIA_COLORS[row % DEUTERANOPIA_COLORS.len()],
+            Palette::Protanopia => {
+                let t = row as f32 / (size as f32 - 1.0).max(1.0);
+                let color = PROTANOPIA_FIRST.lerp(PROTANOPIA_LAST, t);
+                Color::rgb(color.x, color.y, color.z)
+            }
+        }
+    }
+}
+
+/// Colors of popups and buttons
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum UiTheme {
+    Light,
+    #[default]
+    Dark,
+}
+
+/// Part of the UI recolored with the UI theme
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ThemeColor {
+    /// popup panel
+    Background,
+    /// box inside a popup
+    Panel,
+    Text,
+    /// secondary text
+    Hint,
+}
+
+impl UiTheme {
+    pub const ALL: [UiTheme; 2] = [UiTheme::Light, UiTheme::Dark];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UiTheme::Light => "Light",
+            UiTheme::Dark => "Dark",
+        }
+    }
+
+    pub fn color(&self, part: ThemeColor) -> Color {
+        match (self, part) {
+            (UiTheme::Light, ThemeColor::Background) => Color::rgb(0.95, 0.95, 0.95),
+            (UiTheme::Light, ThemeColor::Panel) => Color::rgb(0.85, 0.85, 0.85),
+            (UiTheme::Light, ThemeColor::Text) => Color::BLACK,
+            (UiTheme::Light, ThemeColor::Hint) => Color::rgb(0.4, 0.4, 0.4),
+            (UiTheme::Dark, ThemeColor::Background) => POPUP_BACKGROUND_COLOR,
+            (UiTheme::Dark, ThemeColor::Panel) => Color::rgb(0.2, 0.2, 0.2),
+            (UiTheme::Dark, ThemeColor::Text) => Color::WHITE,
+            (UiTheme::Dark, ThemeColor::Hint) => Color::GRAY,
+        }
+    }
+
+    /// Tint of button images, darkened on hover and press
+    pub fn button_color(&self) -> Color {
+        match self {
+            UiTheme::Light => Color::rgb(0.85, 0.85, 0.85),
+            UiTheme::Dark => BUTTON_WHITE,
+        }
+    }
+}
+
+/// Color of the texts drawn over the scene
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SceneTextColor {
+    #[default]
+    #[serde(alias = "Light")]
+    Black,
+    #[serde(alias = "Dark")]
+    White,
+}
+
+impl SceneTextColor {
+    pub const ALL: [SceneTextColor; 2] = [SceneTextColor::Black, SceneTextColor::White];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SceneTextColor::Black => "Black",
+            SceneTextColor::White => "White",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            SceneTextColor::Black => Color::BLACK,
+            SceneTextColor::White => Color::WHITE,
+        }
+    }
+}
+
+/// Background and lighting presets
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Environment {
+    #[default]
+    Default,
+    Studio,
+    Sunset,
+    Night,
+}
+
+impl Environment {
+    pub const ALL: [Environment; 4] = [
+        Environment::Default,
+        Environment::Studio,
+        Environment::Sunset,
+        Environment::Night,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Environment::Default => "Default",
+            Environment::Studio => "Studio",
+            Environment::Sunset => "Sunset",
+            Environment::Night => "Night",
+        }
+    }
+
+    fn background(&self) -> Color {
+        match self {
+            Environment::Default => Color::rgb(0.4, 0.4, 0.4),
+            Environment::Studio => Color::rgb(0.85, 0.85, 0.85),
+            Environment::Sunset => Color::rgb(0.55, 0.35, 0.3),
+            Environment::Night => Color::rgb(0.05, 0.07, 0.15),
+        }
+    }
+
+    /// color and illuminance of the directional light
+    fn light(&self) -> (Color, f32) {
+        match self {
+            Environment::Default => (Color::WHITE, 100000.0),
+            Environment::Studio => (Color::WHITE, 80000.0),
+            Environment::Sunset => (Color::rgb(1.0, 0.75, 0.5), 70000.0),
+            Environment::Night => (Color::rgb(0.6, 0.7, 1.0), 30000.0),
+        }
+    }
+
+    /// color and brightness of the ambient light
+    fn ambient(&self) -> (Color, f32) {
+        match self {
+            Environment::Default => (Color::WHITE, 0.05),
+            Environment::Studio => (Color::WHITE, 0.3),
+            Environment::Sunset => (Color::rgb(1.0, 0.8, 0.7), 0.1),
+            Environment::Night => (Color::rgb(0.5, 0.6, 1.0), 0.15),
+        }
+    }
+}
+
+/// Previous or next of `all` from `current`, wrapping around
+pub fn cycle<T: Copy + PartialEq>(all: &[T], current: T, forward: bool) -> T {
+    let i = all.iter().position(|value| *value == current).unwrap_or(0);
+    match forward {
+        true => all[(i + 1) % all.len()],
+        false => all[(i + all.len() - 1) % all.len()],
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct Theme {
+    pub palette: Palette,
+    pub ui: UiTheme,
+    pub scene_text: SceneTextColor,
+    pub environment: Environment,
+}
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system_to_stage(StartupStage::PostStartup, setup_environment)
+            .add_system(apply_theme)
+            .add_system(apply_text_theme)
+            .add_system(apply_ui_theme)
+            .add_system(apply_button_theme);
+    }
+}
+
+fn set_environment(
+    environment: Environment,
+    clear_color: &mut ClearColor,
+    ambient_light: &mut AmbientLight,
+    light_query: &mut Query<&mut DirectionalLight>,
+) {
+    clear_color.0 = environment.background();
+    (ambient_light.color, ambient_light.brightness) = environment.ambient();
+    for mut light in light_query.iter_mut() {
+        (light.color, light.illuminance) = environment.light();
+    }
+}
+
+/// The light is spawned in game setup
+fn setup_environment(
+    settings: Res<Settings>,
+    mut clear_color: ResMut<ClearColor>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut light_query: Query<&mut DirectionalLight>,
+) {
+    set_environment(
+        settings.theme.environment,
+        &mut clear_color,
+        &mut ambient_light,
+        &mut light_query,
+    );
+}
+
+/// Recolors blocks and the scene when the theme changed
+fn apply_theme(
+    mut settings_changed_event: EventReader<SettingsChangedEvent>,
+    settings: Res<Settings>,
+    game_query: Query<&GameState>,
+    material_query: Query<&Handle<StandardMaterial>, With<BlockMesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut clear_color: ResMut<ClearColor>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut light_query: Query<&mut DirectionalLight>,
+) {
+    let (mut palette_changed, mut environment_changed) = (false, false);
+    for event in settings_changed_event.iter() {
+        palette_changed |= event.previous.theme.palette != settings.theme.palette;
+        environment_changed |= event.previous.theme.environment != settings.theme.environment;
+    }
+
+    if palette_changed {
+        let game = game_query.single();
+        for block in game.board.0.iter().flatten().flatten() {
+            let row = (block.goal as usize - 1) / game.size;
+            if let Some(material) = material_query
+                .get(block.entity)
+                .ok()
+                .and_then(|handle| materials.get_mut(handle))
+            {
+                material.base_color = settings.theme.palette.block_color(row, game.size);
+            }
+        }
+    }
+    if environment_changed {
+        set_environment(
+            settings.theme.environment,
+            &mut clear_color,
+            &mut ambient_light,
+            &mut light_query,
+        );
+    }
+}
+
+/// Texts drawn over the scene follow the scene text color, also when spawned later.
+/// Texts in popups follow the UI theme instead
+fn apply_text_theme(
+    settings: Res<Settings>,
+    mut text_query: Query<
+        (&mut Text, &MyTextType, ChangeTrackers<MyTextType>),
+        Without<ThemeColor>,
+    >,
+) {
+    let color = settings.theme.scene_text.color();
+    for (mut text, text_type, tracker) in text_query.iter_mut() {
+        if !matches!(
+            text_type,
+            MyTextType::PlayerInfo | MyTextType::GameClear | MyTextType::Date
+        ) || !(settings.is_changed() || tracker.is_added())
+        {
+            continue;
+        }
+        for section in text.sections.iter_mut() {
+            if section.style.color != color {
+                section.style.color = color;
+            }
+        }
+    }
+}
+
+/// Popups follow the UI theme, also when spawned later
+fn apply_ui_theme(
+    settings: Res<Settings>,
+    mut query: Query<(
+        &ThemeColor,
+        ChangeTrackers<ThemeColor>,
+        Option<&mut BackgroundColor>,
+        Option<&mut Text>,
+    )>,
+) {
+    for (part, tracker, background_color, text) in query.iter_mut() {
+        if !(settings.is_changed() || tracker.is_added()) {
+            continue;
+        }
+        let color = settings.theme.ui.color(*part);
+        if let Some(mut background_color) = background_color {
+            if background_color.0 != color {
+                background_color.0 = color;
+            }
+        }
+        if let Some(mut text) = text {
+            for section in text.sections.iter_mut() {
+                if section.style.color != color {
+                    section.style.color = color;
+                }
+            }
+        }
+    }
+}
+
+/// Buttons spawned white or in the previous button color take the button color of the UI theme,
+/// colored buttons such as cleared dates are kept
+fn apply_button_theme(
+    mut settings_changed_event: EventReader<SettingsChangedEvent>,
+    settings: Res<Settings>,
+    mut button_query: Query<(&mut BackgroundColor, ChangeTrackers<Button>)>,
+) {
+    let mut previous = None;
+    for event in settings_changed_event.iter() {
+        if event.previous.theme.ui != settings.theme.ui {
+            previous = Some(event.previous.theme.ui);
+        }
+    }
+
+    let to = settings.theme.ui.button_color();
+    for (mut color, tracker) in button_query.iter_mut() {
+        let from = match (tracker.is_added(), previous) {
+            (true, _) => BUTTON_WHITE,
+            (false, Some(previous)) => previous.button_color(),
+            (false, None) => continue,
+        };
+        if from == to {
+            continue;
+        }
+        if let Some(mul) = [1.0, BUTTON_HOVER_MUL, BUTTON_PRESS_MUL]
+            .into_iter()
+            .find(|mul| color.0 == from * *mul)
+        {
+            color.0 = to * mul;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::BUTTON_GREEN;
+
+    #[test]
+    fn ui_theme_and_scene_text_color_are_read_apart() {
+        let theme: Theme = serde_json::from_str(r#"{"ui": "Light"}"#).unwrap();
+        assert_eq!(theme.ui, UiTheme::Light);
+        assert_eq!(theme.scene_text, SceneTextColor::Black);
+        let theme: Theme = serde_json::from_str(r#"{"scene_text": "Dark"}"#).unwrap();
+        assert_eq!(theme.ui, UiTheme::Dark);
+        assert_eq!(theme.scene_text, SceneTextColor::White);
+    }
+
+    #[test]
+    fn buttons_follow_the_ui_theme() {
+        let mut app = App::new();
+        app.add_event::<SettingsChangedEvent>()
+            .insert_resource(Settings::default())
+            .add_system(apply_button_theme);
+        let white = app
+            .world
+            .spawn((Button, BackgroundColor(BUTTON_WHITE)))
+            .id();
+        let hovered = app
+            .world
+            .spawn((Button, BackgroundColor(BUTTON_WHITE * BUTTON_HOVER_MUL)))
+            .id();
+        let green = app
+            .world
+            .spawn((Button, BackgroundColor(BUTTON_GREEN)))
+            .id();
+        app.update();
+
+        let previous = app.world.resource::<Settings>().clone();
+        app.world.resource_mut::<Settings>().theme.ui = UiTheme::Light;
+        app.world
+            .resource_mut::<Events<SettingsChangedEvent>>()
+            .send(SettingsChangedEvent { previous });
+        app.update();
+
+        let color = |entity| app.world.get::<BackgroundColor>(entity).unwrap().0;
+        let light = UiTheme::Light.button_color();
+        assert_eq!(color(white), light);
+        assert_eq!(color(hovered), light * BUTTON_HOVER_MUL);
+        assert_eq!(color(green), BUTTON_GREEN);
+    }
+}
@@ -4,7 +4,7 @@ use bevy::{prelude::*, ui::FocusPolicy};
 use serde::{Deserialize, Serialize};
 
 use crate::game::{GameState, MoveTimer};
-use crate::network::{Network, NetworkChannel};
+use crate::network::{LeaderboardMode, Network, NetworkChannel};
 use crate::player::{PlayLog, PlayerInfo, PlayerState};
 use crate::ui::TEXT_SIZE;
 use crate::MyButtonType;
@@ -21,6 +21,15 @@ pub enum GameMode {
 }
 
 impl GameMode {
+    /// competitive modes which have global leaderboards
+    pub fn leaderboard_mode(&self) -> Option<LeaderboardMode> {
+        match self {
+            GameMode::TimeAttack => Some(LeaderboardMode::TimeAttack),
+            GameMode::MinimalMovement => Some(LeaderboardMode::MinimalMovement),
+            _ => None,
+        }
+    }
+
     pub fn entry_handler(
         &self,
         player_info: &mut PlayerInfo,
@@ -167,6 +176,20 @@ fn spawn_game_mode_ui(
                     "Statistics".to_string(),
                     font.clone(),
                 );
+
+                // rankings button
+                spawn_image_button(
+                    parent,
+                    UiRect {
+                        top: Val::Px(395.0),
+                        left: Val::Px(50.0),
+                        ..default()
+                    },
+                    MyButtonType::Rankings,
+                    asset_server.load("images/button_rankings.png").into(),
+                    "Rankings".to_string(),
+                    font.clone(),
+                );
             }
             GameMode::DailyPuzzle => {
                 // date text
@@ -1,4 +1,4 @@
-use crate::{daily_puzzle_info::DailyPuzzleInfo, player::PlayerState, ui::*};
+use crate::{daily_puzzle_info::DailyPuzzleInfo, game::GameState, player::PlayerState, ui::*};
 
 #[derive(Component)]
 pub struct GameUI;
@@ -124,6 +124,38 @@ fn setup_game_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                 asset_server.load("images/button_medium.png").into(),
             );
 
+            // camera view button
+            spawn_button(
+                parent,
+                UiRect {
+                    right: Val::Px(50.0),
+                    top: Val::Px(290.0),
+                    ..default()
+                },
+                Size::new(Val::Px(100.0), Val::Px(100.0)),
+                "View".to_string(),
+                font.clone(),
+                MyButtonType::CameraView,
+                Some(MyTextType::CameraView),
+                asset_server.load("images/button_medium.png").into(),
+            );
+
+            // orientation assist button
+            spawn_button(
+                parent,
+                UiRect {
+                    right: Val::Px(50.0),
+                    top: Val::Px(410.0),
+                    ..default()
+                },
+                Size::new(Val::Px(100.0), Val::Px(100.0)),
+                "Assist".to_string(),
+                font.clone(),
+                MyButtonType::OrientationAssist,
+                Some(MyTextType::OrientationAssist),
+                asset_server.load("images/button_medium.png").into(),
+            );
+
             // player info
             parent.spawn((
                 TextBundle::from_section(
@@ -154,6 +186,7 @@ fn spawn_clear_ui(
     asset_server: Res<AssetServer>,
     game_mode: Res<State<GameMode>>,
     daily_puzzle_info_query: Query<&DailyPuzzleInfo>,
+    game_query: Query<&GameState>,
 ) {
     let font = asset_server.load("fonts/Quicksand-Bold.ttf");
 
@@ -178,23 +211,33 @@ fn spawn_clear_ui(
             MyTextType::GameClear,
         ));
 
-        if *game_mode.current() == GameMode::DailyPuzzle {
-            let daily_puzzle_info = daily_puzzle_info_query.single();
-            if daily_puzzle_info.current_date == daily_puzzle_info.last_date {
-                // enroll score button
-                spawn_image_button(
-                    parent,
-                    UiRect {
-                        right: Val::Px(450.0),
-                        top: Val::Px(50.0),
-                        ..default()
-                    },
-                    MyButtonType::PopupEnrollScore,
-                    asset_server.load("images/button_enroll_score.png").into(),
-                    "Enroll Score".to_string(),
-                    font.clone(),
-                );
+        let daily_puzzle_info = daily_puzzle_info_query.single();
+        let enrollable = match game_mode.current() {
+            // generated boards differ from the server's
+            GameMode::DailyPuzzle => {
+                daily_puzzle_info.current_date == daily_puzzle_info.last_date
+                    && !daily_puzzle_info.is_generated(daily_puzzle_info.current_date)
+            }
+            // only boards shuffled by server can be enrolled
+            game_mode => {
+                game_mode.leaderboard_mode().is_some()
+                    && game_query.single().leaderboard_attempt.is_some()
             }
+        };
+        if enrollable {
+            // enroll score button
+            spawn_image_button(
+                parent,
+                UiRect {
+                    right: Val::Px(450.0),
+                    top: Val::Px(50.0),
+                    ..default()
+                },
+                MyButtonType::PopupEnrollScore,
+                asset_server.load("images/button_enroll_score.png").into(),
+                "Enroll Score".to_string(),
+                font.clone(),
+            );
         }
     });
 }
@@ -3,17 +3,52 @@ use std::time::Duration;
 use chrono::Datelike;
 
 use crate::{
-    buffered_input::{InputBuffer, InputHandler, InputInversionFlag, InputTimer, MoveImmediate},
+    buffered_input::{InputBuffer, InputHandler, InputTimer},
+    camera::CameraOrbit,
     daily_puzzle_info::DailyPuzzleInfo,
-    game::{EasyMode, GameState, MoveTimer},
-    local_storage::LocalStorage,
-    network::NetworkChannel,
-    player::{PlayLog, PlayerInfo, PlayerState},
-    statistics_manager::StatisticsManager,
+    game::{GameState, MoveTimer},
+    network::{LeaderboardKey, NetworkChannel},
+    player::{PlayLog, PlayerIdentity, PlayerInfo, PlayerState},
+    settings::Settings,
+    statistics_manager::{ImportError, StatisticsManager},
     ui::*,
     utils::*,
 };
 
+/// Shuffle of the button and the gamepad,
+/// boards of competitive modes are shuffled by server to be enrolled
+pub fn shuffle(
+    game: &mut GameState,
+    transforms: &mut Query<&mut Transform>,
+    play_log: &mut PlayLog,
+    game_mode: &GameMode,
+    settings: &Settings,
+    player_identity: &PlayerIdentity,
+    player_state: &mut ResMut<State<PlayerState>>,
+    network_channel: &Res<NetworkChannel>,
+) {
+    play_log.reset();
+    match game_mode.leaderboard_mode() {
+        Some(mode) => crate::network::Network::start_leaderboard_attempt(
+            LeaderboardKey {
+                mode,
+                size: game.size,
+                easy_mode: settings.easy_mode,
+            },
+            player_identity.0.id.clone(),
+            player_state,
+            network_channel,
+        ),
+        None => {
+            game.shuffle(transforms);
+            game.is_shuffled = true;
+            if *player_state.current() != PlayerState::Shuffled {
+                let _ = player_state.set(PlayerState::Shuffled);
+            }
+        }
+    }
+}
+
 pub fn game_ui_button_system(
     mut interaction_query: Query<
         (
@@ -24,15 +59,11 @@ pub fn game_ui_button_system(
         ),
         (Changed<Interaction>, With<Button>),
     >,
-    text_query: Query<(&Text, &MyTextType)>,
+    mut text_query: Query<(&mut Text, &MyTextType)>,
     mut transforms: Query<&mut Transform>,
     mut move_timer: ResMut<MoveTimer>,
     mut game_query: Query<&mut GameState>,
-    mut input_system: Query<(
-        &mut InputBuffer,
-        &mut InputInversionFlag,
-        &mut MoveImmediate,
-    )>,
+    mut input_buffer: Query<&mut InputBuffer>,
     mut play_log: Query<&mut PlayLog>,
     mut player_state: ResMut<State<PlayerState>>,
     mut input_timer: ResMut<InputTimer>,
@@ -42,12 +73,18 @@ pub fn game_ui_button_system(
     network_channel: Res<NetworkChannel>,
     mut delete_statistics_event: EventWriter<DeleteStatisticsEvent>,
     player_info_query: Query<&PlayerInfo>,
+    (player_identity, game_mode, mut settings, mut camera_orbit): (
+        Res<PlayerIdentity>,
+        Res<State<GameMode>>,
+        ResMut<Settings>,
+        ResMut<CameraOrbit>,
+    ),
 ) {
+    let button_color = settings.theme.ui.button_color();
     let mut game = game_query.single_mut();
     let daily_puzzle_info = daily_puzzle_info_query.single();
 
-    let (mut input_buffer, mut input_reveresion_flag, mut move_immediate) =
-        input_system.single_mut();
+    let mut input_buffer = input_buffer.single_mut();
 
     // button interactions
     for (interaction, mut color, ui_image, button_type) in &mut interaction_query {
@@ -62,44 +99,86 @@ pub fn game_ui_button_system(
                             let _ = player_state.set(PlayerState::Idle);
                         }
                     }
-                    MyButtonType::Shuffle => {
-                        game.shuffle(&mut transforms);
-                        play_log.single_mut().reset();
-                        game.is_shuffled = true;
-                        if *player_state.current() != PlayerState::Shuffled {
-                            let _ = player_state.set(PlayerState::Shuffled);
+                    MyButtonType::Shuffle => shuffle(
+                        &mut game,
+                        &mut transforms,
+                        &mut play_log.single_mut(),
+                        game_mode.current(),
+                        &settings,
+                        &player_identity,
+                        &mut player_state,
+                        &network_channel,
+                    ),
+                    MyButtonType::AnimationToggle => match settings.move_immediate {
+                        true => {
+                            settings.move_immediate = false;
+                            ui_image.unwrap().0 = asset_server.load("images/button_toggle_on.png");
                         }
-                    }
-                    MyButtonType::AnimationToggle => {
-                        match move_immediate.0 {
-                            true => {
-                                move_immediate.0 = false;
-                                ui_image.unwrap().0 =
-                                    asset_server.load("images/button_toggle_on.png");
-                            }
-                            false => {
-                                move_immediate.0 = true;
-                                ui_image.unwrap().0 =
-                                    asset_server.load("images/button_toggle_off.png");
-                            }
+                        false => {
+                            settings.move_immediate = true;
+                            ui_image.unwrap().0 = asset_server.load("images/button_toggle_off.png");
+                        }
+                    },
+                    MyButtonType::InputInversion => match settings.input_inversion {
+                        true => {
+                            settings.input_inversion = false;
+                            ui_image.unwrap().0 = asset_server.load("images/button_toggle_off.png");
+                        }
+                        false => {
+                            settings.input_inversion = true;
+                            ui_image.unwrap().0 = asset_server.load("images/button_toggle_on.png");
+                        }
+                    },
+                    MyButtonType::InterruptAnimation => match settings.interrupt_animation {
+                        true => {
+                            settings.interrupt_animation = false;
+                            ui_image.unwrap().0 = asset_server.load("images/button_toggle_off.png");
+                        }
+                        false => {
+                            settings.interrupt_animation = true;
+                            ui_image.unwrap().0 = asset_server.load("images/button_toggle_on.png");
+                        }
+                    },
+                    MyButtonType::AssistArrows => match settings.assist_arrows {
+                        true => {
+                            settings.assist_arrows = false;
+                            ui_image.unwrap().0 = asset_server.load("images/button_toggle_off.png");
+                        }
+                        false => {
+                            settings.assist_arrows = true;
+                            ui_image.unwrap().0 = asset_server.load("images/button_toggle_on.png");
+                        }
+                    },
+                    MyButtonType::ExportSettings => {
+                        let clipboard = web_sys::window().unwrap().navigator().clipboard().unwrap();
+                        let _ = clipboard.write_text(&settings.export());
+                        for (mut text, _) in text_query
+                            .iter_mut()
+                            .filter(|(_, text_type)| **text_type == MyTextType::ShareURL)
+                        {
+                            text.sections[0].value = "Settings copied!\n".into();
+                            text.sections[1].value = "Paste them on your other device".into();
                         }
-                        LocalStorage::set_move_immediate(&move_immediate.0);
                     }
-                    MyButtonType::InputInversion => {
-                        play_log.single_mut().reset();
-                        match input_reveresion_flag.0 {
-                            true => {
-                                input_reveresion_flag.0 = false;
-                                ui_image.unwrap().0 =
-                                    asset_server.load("images/button_toggle_off.png");
-                            }
-                            false => {
-                                input_reveresion_flag.0 = true;
-                                ui_image.unwrap().0 =
-                                    asset_server.load("images/button_toggle_on.png");
+                    MyButtonType::ImportSettings => {
+                        let window = web_sys::window().unwrap();
+                        if let Ok(Some(text)) =
+                            window.prompt_with_message("Paste exported settings")
+                        {
+                            match Settings::import(&text) {
+                                Ok(imported) => {
+                                    *settings = imported;
+                                    // spawn the popup again with the imported settings
+                                    let _ = player_state.restart();
+                                }
+                                Err(error) => {
+                                    let _ = window.alert_with_message(&format!(
+                                        "Import failed, invalid settings: {}",
+                                        error
+                                    ));
+                                }
                             }
                         }
-                        LocalStorage::set_input_inversion(&input_reveresion_flag.0);
                     }
                     MyButtonType::ModeSelection => {
                         let _ = player_state.push(PlayerState::ModeSelectionPopup);
@@ -110,6 +189,10 @@ pub fn game_ui_button_system(
                     MyButtonType::Difficulty => {
                         let _ = player_state.push(PlayerState::DifficultyPopup);
                     }
+                    MyButtonType::CameraView => camera_orbit.next_preset(),
+                    MyButtonType::OrientationAssist => {
+                        settings.orientation_assist = !settings.orientation_assist;
+                    }
                     MyButtonType::Share => {
                         let board_string = board_to_string(&transforms, &mut game);
                         let puzzle_key = board_string.into_key();
@@ -121,13 +204,11 @@ pub fn game_ui_button_system(
                         );
                     }
                     MyButtonType::Undo => InputHandler::undo(
-                        input_reveresion_flag.0,
                         &mut input_buffer,
                         &mut play_log.single_mut(),
                         &mut input_timer,
                     ),
                     MyButtonType::Redo => InputHandler::redo(
-                        input_reveresion_flag.0,
                         &mut input_buffer,
                         &mut play_log.single_mut(),
                         &mut input_timer,
@@ -151,15 +232,44 @@ pub fn game_ui_button_system(
                                 if !user_name.is_empty() {
                                     let player_info = player_info_query.single().get_player_info();
                                     info!("Enroll Score: {}", user_name);
-                                    let daily_puzzle_info = daily_puzzle_info_query.single();
-                                    crate::network::Network::enroll_daily_ranking(
-                                        daily_puzzle_info.current_date,
-                                        user_name,
-                                        player_info.0,
-                                        player_info.1,
-                                        &mut player_state,
-                                        &network_channel,
-                                    );
+                                    match (
+                                        game_mode.current().leaderboard_mode(),
+                                        game.leaderboard_attempt,
+                                    ) {
+                                        (Some(mode), Some(attempt)) => {
+                                            crate::network::Network::enroll_leaderboard_score(
+                                                LeaderboardKey {
+                                                    mode,
+                                                    size: game.size,
+                                                    easy_mode: settings.easy_mode,
+                                                },
+                                                player_identity.0.clone(),
+                                                user_name,
+                                                player_info.0,
+                                                player_info.1,
+                                                attempt,
+                                                play_log.single().move_log(),
+                                                play_log.single().assisted(),
+                                                &mut player_state,
+                                                &network_channel,
+                                            );
+                                        }
+                                        _ => {
+                                            let daily_puzzle_info =
+                                                daily_puzzle_info_query.single();
+                                            crate::network::Network::enroll_daily_ranking(
+                                                daily_puzzle_info.current_date,
+                                                player_identity.0.clone(),
+                                                user_name,
+                                                player_info.0,
+                                                player_info.1,
+                                                play_log.single().move_log(),
+                                                play_log.single().assisted(),
+                                                &mut player_state,
+                                                &network_channel,
+                                            );
+                                        }
+                                    }
                                 }
                             }
                         }
@@ -177,6 +287,54 @@ pub fn game_ui_button_system(
                         let statistics_manager = statistics_manager_query.single();
                         statistics_manager.export();
                     }
+                    MyButtonType::ExportCsv | MyButtonType::ExportJson => {
+                        let statistics_manager = statistics_manager_query.single();
+                        let export_string = match button_type {
+                            MyButtonType::ExportCsv => statistics_manager.export_csv(),
+                            _ => statistics_manager.export_json(),
+                        };
+                        let clipboard = web_sys::window().unwrap().navigator().clipboard().unwrap();
+                        let _ = clipboard.write_text(&export_string);
+                    }
+                    MyButtonType::PrevSession | MyButtonType::NextSession => {
+                        let offset = match button_type {
+                            MyButtonType::PrevSession => -1,
+                            _ => 1,
+                        };
+                        statistics_manager_query.single_mut().switch_session(offset);
+                        // spawn the popup again with records of the session
+                        let _ = player_state.restart();
+                    }
+                    MyButtonType::NewSession => {
+                        let window = web_sys::window().unwrap();
+                        if let Ok(Some(name)) = window.prompt_with_message("New session name") {
+                            statistics_manager_query.single_mut().add_session(&name);
+                            let _ = player_state.restart();
+                        }
+                    }
+                    MyButtonType::ImportStatistics => {
+                        let window = web_sys::window().unwrap();
+                        if let Ok(Some(text)) =
+                            window.prompt_with_message("Paste exported CSV or JSON statistics")
+                        {
+                            let message = match statistics_manager_query.single_mut().import(&text)
+                            {
+                                Ok(added) => format!("Imported {} records", added),
+                                Err(ImportError::Json(error)) => {
+                                    format!("Import failed, invalid JSON: {}", error)
+                                }
+                                Err(ImportError::Csv(line)) => {
+                                    format!("Import failed, invalid CSV at line {}", line)
+                                }
+                                Err(ImportError::Mode(i)) => {
+                                    format!("Import failed, record {} has no statistics mode", i)
+                                }
+                            };
+                            let _ = window.alert_with_message(&message);
+                            // spawn the popup again with the merged records
+                            let _ = player_state.restart();
+                        }
+                    }
                     MyButtonType::LoadURL => {
                         for (text, &text_type) in text_query.iter() {
                             if let MyTextType::TextInputBox(_) = text_type {
@@ -195,11 +353,26 @@ pub fn game_ui_button_system(
                         statistics_manager.delete_statistics();
                         delete_statistics_event.send_default();
                     }
+                    MyButtonType::CopyPlayerLink => {
+                        let player_url = format!(
+                            "dice15puzzle.haje.org/?player={}",
+                            player_identity.0.to_link_key()
+                        );
+                        let clipboard = web_sys::window().unwrap().navigator().clipboard().unwrap();
+                        let _ = clipboard.write_text(&player_url);
+                        for (mut text, _) in text_query
+                            .iter_mut()
+                            .filter(|(_, text_type)| **text_type == MyTextType::ShareURL)
+                        {
+                            text.sections[0].value = "Player link copied!\n".into();
+                            text.sections[1].value = "Open it on your other device".into();
+                        }
+                    }
                 }
-                *color = (BUTTON_WHITE * BUTTON_PRESS_MUL).into();
+                *color = (button_color * BUTTON_PRESS_MUL).into();
             }
-            Interaction::Hovered => *color = (BUTTON_WHITE * BUTTON_HOVER_MUL).into(),
-            Interaction::None => *color = BUTTON_WHITE.into(),
+            Interaction::Hovered => *color = (button_color * BUTTON_HOVER_MUL).into(),
+            Interaction::None => *color = button_color.into(),
         }
     }
 }
@@ -211,6 +384,10 @@ pub fn game_ui_text_system(
     daily_puzzle_info_query: Query<&DailyPuzzleInfo>,
     mut char_evr: EventReader<ReceivedCharacter>,
     keyboard_input: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    player_state: Res<State<PlayerState>>,
+    difficulty_selection: Option<Res<DifficultySelection>>,
+    camera_orbit: Res<CameraOrbit>,
 ) {
     let daily_puzzle_info = daily_puzzle_info_query.single();
 
@@ -254,8 +431,15 @@ pub fn game_ui_text_system(
                 }
             }
             MyTextType::Difficulty => {
-                let size = LocalStorage::get_board_size().unwrap_or_default().0;
-                let easy_mode = LocalStorage::get_easy_mode().unwrap_or(EasyMode(false)).0;
+                // show the selection while the difficulty popup is open
+                let (size, easy_mode) = match difficulty_selection {
+                    Some(ref selection)
+                        if *player_state.current() == PlayerState::DifficultyPopup =>
+                    {
+                        (selection.board_size, selection.easy_mode)
+                    }
+                    _ => (settings.board_size, settings.easy_mode),
+                };
                 text.sections[0].value = format!(
                     "{} x {}\n{}",
                     size,
@@ -263,6 +447,26 @@ pub fn game_ui_text_system(
                     if easy_mode { "Easy" } else { "Hard" }
                 );
             }
+            MyTextType::OrientationAssist => {
+                if settings.is_changed() {
+                    text.sections[0].value = format!(
+                        "Assist\n{}",
+                        if settings.orientation_assist {
+                            "On"
+                        } else {
+                            "Off"
+                        }
+                    );
+                }
+            }
+            MyTextType::CameraView => {
+                if camera_orbit.is_changed() {
+                    text.sections[0].value = format!(
+                        "View\n{}",
+                        camera_orbit.preset.map_or("Free", |preset| preset.name())
+                    );
+                }
+            }
             _ => {}
         }
     }
@@ -285,8 +489,8 @@ pub fn button_hover_system(
     for (interaction, tracker, mut interaction_history, mut hover_timer, children) in
         interaction_query.iter_mut()
     {
-        assert_eq!(children.len(), 1);
-        let mut info_visibility = visibility_query.get_mut(children[0]).unwrap();
+        // button info is spawned as the last child of the button
+        let mut info_visibility = visibility_query.get_mut(*children.last().unwrap()).unwrap();
 
         if tracker.is_changed() {
             interaction_history.prev = interaction_history.curr;
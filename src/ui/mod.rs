@@ -11,6 +11,7 @@ mod popup_rankings;
 mod popup_settings;
 mod popup_statistics;
 mod scroll_bar;
+mod statistics_chart;
 mod text_input_box;
 
 pub use bevy::prelude::*;
@@ -27,6 +28,7 @@ pub use popup_rankings::*;
 pub use popup_settings::*;
 pub use popup_statistics::*;
 pub use scroll_bar::*;
+pub use statistics_chart::*;
 pub use text_input_box::*;
 
 pub const TEXT_SIZE: f32 = 40.0;
@@ -43,9 +45,13 @@ pub enum MyButtonType {
     Shuffle,
     AnimationToggle,
     InputInversion,
+    InterruptAnimation,
     ModeSelection,
     Settings,
     Difficulty,
+    CameraView,
+    OrientationAssist,
+    AssistArrows,
     Share,
     Undo,
     Redo,
@@ -56,8 +62,17 @@ pub enum MyButtonType {
     EnrollScore,
     Restart,
     Export,
+    ExportCsv,
+    ExportJson,
+    ImportStatistics,
+    PrevSession,
+    NextSession,
+    NewSession,
+    ExportSettings,
+    ImportSettings,
     LoadURL,
     DeleteStatistics,
+    CopyPlayerLink,
 }
 
 #[derive(Debug, Component, PartialEq, Eq, Clone, Copy)]
@@ -68,6 +83,9 @@ pub enum MyTextType {
     Date,
     ShareURL,
     TextInputBox(usize),
-    EnrollDailyScoreResult,
+    EnrollScoreResult,
     Difficulty,
+    CameraView,
+    OrientationAssist,
+    RankingPage,
 }
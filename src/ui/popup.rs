@@ -1,4 +1,4 @@
-use crate::{player::PlayerState, ui::*};
+use crate::{player::PlayerState, settings::Settings, theme::ThemeColor, ui::*};
 
 pub const POPUP_BACKGROUND_COLOR: Color = Color::rgb(0.1, 0.1, 0.1);
 
@@ -41,6 +41,7 @@ impl Plugin for PopupPlugin {
             )
             .add_system_set(
                 SystemSet::on_update(PlayerState::SettingsPopup)
+                    .with_system(popup_system_settings)
                     .with_system(popup_close_button_system),
             )
             .add_system_set(
@@ -103,7 +104,9 @@ fn popup_close_button_system(
     >,
     mut player_state: ResMut<State<PlayerState>>,
     keyboard_input: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
 ) {
+    let button_color = settings.theme.ui.button_color();
     let mut close = false;
 
     for (interaction, mut color, popup) in &mut interaction_query {
@@ -112,9 +115,9 @@ fn popup_close_button_system(
         }
         if popup.is_none() {
             *color = match *interaction {
-                Interaction::Clicked => (BUTTON_WHITE * BUTTON_PRESS_MUL).into(),
-                Interaction::Hovered => (BUTTON_WHITE * BUTTON_HOVER_MUL).into(),
-                Interaction::None => BUTTON_WHITE.into(),
+                Interaction::Clicked => (button_color * BUTTON_PRESS_MUL).into(),
+                Interaction::Hovered => (button_color * BUTTON_HOVER_MUL).into(),
+                Interaction::None => button_color.into(),
             };
         }
     }
@@ -170,17 +173,20 @@ pub fn spawn_popup_panel(
         .with_children(|parent| {
             // UI panel
             parent
-                .spawn(NodeBundle {
-                    style: Style {
-                        position_type: PositionType::Absolute,
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::Center,
-                        size: Size::new(Val::Px(600.0), Val::Px(600.0)),
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            size: Size::new(Val::Px(600.0), Val::Px(600.0)),
+                            ..default()
+                        },
+                        background_color: POPUP_BACKGROUND_COLOR.into(),
                         ..default()
                     },
-                    background_color: POPUP_BACKGROUND_COLOR.into(),
-                    ..default()
-                })
+                    ThemeColor::Background,
+                ))
                 .with_children(|parent| {
                     // close button
                     parent
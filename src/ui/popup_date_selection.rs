@@ -1,6 +1,12 @@
 use crate::{
-    daily_puzzle_info::DailyPuzzleInfo, game::GameState, network::NetworkChannel,
-    player::PlayerState, *,
+    daily_puzzle_info::{DailyPuzzleInfo, DailyRecord},
+    game::GameState,
+    network::NetworkChannel,
+    player::PlayerState,
+    settings::Settings,
+    theme::ThemeColor,
+    utils::duration_to_string,
+    *,
 };
 use bevy::prelude::*;
 use chrono::{Datelike, Month, Months, NaiveDate};
@@ -12,6 +18,12 @@ pub struct CalendarUI(NaiveDate);
 #[derive(Component)]
 pub struct MonthYearText;
 
+#[derive(Component)]
+pub struct CalendarStatisticsText;
+
+const CALENDAR_CELL_SIZE: f32 = 50.0;
+const STATISTICS_TEXT_SIZE: f32 = 25.0;
+
 #[derive(Component)]
 pub enum PopupDateSelectionButtonType {
     MonthPrev,
@@ -49,7 +61,7 @@ pub fn spawn_popup_date_selection(
                     "Date Selection".to_string(),
                     font.clone(),
                     Color::WHITE,
-                    None,
+                    Some(ThemeColor::Text),
                 );
 
                 // month, year text
@@ -69,37 +81,76 @@ pub fn spawn_popup_date_selection(
                     ),
                     font.clone(),
                     Color::WHITE,
-                    Some(MonthYearText),
+                    Some((MonthYearText, ThemeColor::Text)),
                 );
 
                 // month prev button
                 spawn_popup_date_selection_button(
                     parent,
                     UiRect {
-                        top: Val::Px(95.0),
-                        left: Val::Px(90.0),
+                        top: Val::Px(100.0),
+                        left: Val::Px(125.0),
                         ..default()
                     },
                     "<".to_string(),
                     font.clone(),
                     PopupDateSelectionButtonType::MonthPrev,
                     BUTTON_WHITE,
+                    None,
                 );
 
                 // month next button
                 spawn_popup_date_selection_button(
                     parent,
                     UiRect {
-                        top: Val::Px(95.0),
-                        right: Val::Px(90.0),
+                        top: Val::Px(100.0),
+                        right: Val::Px(125.0),
                         ..default()
                     },
                     ">".to_string(),
                     font.clone(),
                     PopupDateSelectionButtonType::MonthNext,
                     BUTTON_WHITE,
+                    None,
                 );
 
+                // streak and month statistics
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            justify_content: JustifyContent::Center,
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                top: Val::Px(160.0),
+                                left: Val::Px(0.0),
+                                right: Val::Px(0.0),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section(
+                                calendar_statistics_to_string(
+                                    daily_puzzle_info,
+                                    current_date,
+                                    first_date,
+                                    last_date,
+                                ),
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: STATISTICS_TEXT_SIZE,
+                                    color: Color::WHITE,
+                                },
+                            )
+                            .with_text_alignment(TextAlignment::CENTER),
+                            CalendarStatisticsText,
+                            ThemeColor::Text,
+                        ));
+                    });
+
                 // calendar
                 parent
                     .spawn((
@@ -108,11 +159,14 @@ pub fn spawn_popup_date_selection(
                             style: Style {
                                 position_type: PositionType::Absolute,
                                 position: UiRect {
-                                    left: Val::Px(90.0),
-                                    bottom: Val::Px(25.0),
+                                    left: Val::Px(125.0),
+                                    bottom: Val::Px(20.0),
                                     ..default()
                                 },
-                                size: Size::new(Val::Px(420.0), Val::Px(420.0)),
+                                size: Size::new(
+                                    Val::Px(7.0 * CALENDAR_CELL_SIZE),
+                                    Val::Px(7.0 * CALENDAR_CELL_SIZE),
+                                ),
                                 ..default()
                             },
                             ..default()
@@ -146,6 +200,14 @@ pub fn popup_system_date_selection(
     >,
     mut calendar_ui_query: Query<(&mut CalendarUI, Entity, &Children)>,
     mut month_year_text_query: Query<&mut Text, (With<MonthYearText>, Without<MyTextType>)>,
+    mut statistics_text_query: Query<
+        &mut Text,
+        (
+            With<CalendarStatisticsText>,
+            Without<MonthYearText>,
+            Without<MyTextType>,
+        ),
+    >,
     asset_server: Res<AssetServer>,
     mut player_state: ResMut<State<PlayerState>>,
     mut date_text_query: Query<(&mut Text, &MyTextType)>,
@@ -153,10 +215,13 @@ pub fn popup_system_date_selection(
     mut daily_puzzle_info_query: Query<&mut DailyPuzzleInfo>,
     mut transforms: Query<&mut Transform>,
     mut game_query: Query<&mut GameState>,
+    settings: Res<Settings>,
 ) {
+    let button_color = settings.theme.ui.button_color();
     let font = asset_server.load("fonts/Quicksand-Bold.ttf");
     let (mut calendar_ui, entity, children) = calendar_ui_query.single_mut();
     let mut month_year_text = month_year_text_query.single_mut();
+    let mut statistics_text = statistics_text_query.single_mut();
     let mut daily_puzzle_info = daily_puzzle_info_query.single_mut();
     let first_date = daily_puzzle_info.first_date;
     let last_date = daily_puzzle_info.last_date;
@@ -189,7 +254,13 @@ pub fn popup_system_date_selection(
                         Month::from_u32(date.month()).unwrap().name(),
                         date.year_ce().1
                     );
-                    *color = (BUTTON_WHITE * BUTTON_PRESS_MUL).into();
+                    statistics_text.sections[0].value = calendar_statistics_to_string(
+                        &daily_puzzle_info,
+                        date,
+                        first_date,
+                        last_date,
+                    );
+                    *color = (button_color * BUTTON_PRESS_MUL).into();
                 }
                 PopupDateSelectionButtonType::MonthNext => {
                     if last_date >= calendar_ui.0.checked_add_months(Months::new(1)).unwrap() {
@@ -214,7 +285,13 @@ pub fn popup_system_date_selection(
                         Month::from_u32(date.month()).unwrap().name(),
                         date.year_ce().1
                     );
-                    *color = (BUTTON_WHITE * BUTTON_PRESS_MUL).into();
+                    statistics_text.sections[0].value = calendar_statistics_to_string(
+                        &daily_puzzle_info,
+                        date,
+                        first_date,
+                        last_date,
+                    );
+                    *color = (button_color * BUTTON_PRESS_MUL).into();
                 }
                 PopupDateSelectionButtonType::Date(date, clear) => {
                     info!("{}", date);
@@ -236,7 +313,7 @@ pub fn popup_system_date_selection(
                             );
                         }
                     }
-                    *color = (if *clear { BUTTON_GREEN } else { BUTTON_WHITE } * BUTTON_PRESS_MUL)
+                    *color = (if *clear { BUTTON_GREEN } else { button_color } * BUTTON_PRESS_MUL)
                         .into();
                 }
             },
@@ -245,10 +322,10 @@ pub fn popup_system_date_selection(
                     if *clear {
                         BUTTON_GREEN
                     } else {
-                        BUTTON_WHITE
+                        button_color
                     }
                 } else {
-                    BUTTON_WHITE
+                    button_color
                 } * BUTTON_HOVER_MUL)
                     .into()
             }
@@ -257,10 +334,10 @@ pub fn popup_system_date_selection(
                     if *clear {
                         BUTTON_GREEN
                     } else {
-                        BUTTON_WHITE
+                        button_color
                     }
                 } else {
-                    BUTTON_WHITE
+                    button_color
                 }
                 .into()
             }
@@ -275,7 +352,7 @@ fn spawn_text(
     text: String,
     font: Handle<Font>,
     color: Color,
-    text_type: Option<MonthYearText>,
+    extra_component: Option<impl Bundle>,
 ) {
     parent
         .spawn(NodeBundle {
@@ -299,8 +376,8 @@ fn spawn_text(
                 },
             )
             .with_text_alignment(TextAlignment::CENTER);
-            if let Some(text_type) = text_type {
-                parent.spawn((text_bundle, text_type));
+            if let Some(component) = extra_component {
+                parent.spawn((text_bundle, component));
             } else {
                 parent.spawn(text_bundle);
             }
@@ -314,36 +391,99 @@ fn spawn_popup_date_selection_button(
     font: Handle<Font>,
     button_type: PopupDateSelectionButtonType,
     color: Color,
+    info: Option<(String, UiRect)>,
 ) {
-    parent
-        .spawn((
-            ButtonBundle {
-                style: Style {
-                    align_items: AlignItems::Center,
-                    justify_content: JustifyContent::Center,
-                    size: Size::new(Val::Px(60.0), Val::Px(60.0)),
-                    position_type: PositionType::Absolute,
-                    position,
-                    ..default()
-                },
-                background_color: color.into(),
+    let mut button = parent.spawn((
+        ButtonBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                size: Size::new(Val::Px(CALENDAR_CELL_SIZE), Val::Px(CALENDAR_CELL_SIZE)),
+                position_type: PositionType::Absolute,
+                position,
                 ..default()
             },
-            button_type,
-        ))
-        .with_children(|parent| {
-            parent.spawn(
-                TextBundle::from_section(
-                    text,
-                    TextStyle {
-                        font,
-                        font_size: TEXT_SIZE,
-                        color: Color::BLACK,
-                    },
-                )
-                .with_text_alignment(TextAlignment::CENTER),
-            );
-        });
+            background_color: color.into(),
+            ..default()
+        },
+        button_type,
+    ));
+    button.with_children(|parent| {
+        parent.spawn(
+            TextBundle::from_section(
+                text,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: TEXT_SIZE,
+                    color: Color::BLACK,
+                },
+            )
+            .with_text_alignment(TextAlignment::CENTER),
+        );
+    });
+    if let Some((info_text, info_position)) = info {
+        button
+            .insert(ButtonInfoBundle::default())
+            .with_children(|parent| {
+                spawn_button_info(parent, info_text, Some(info_position), font);
+            });
+    }
+}
+
+/// Streaks and statistics of the month shown in the calendar
+fn calendar_statistics_to_string(
+    daily_puzzle_info: &DailyPuzzleInfo,
+    month: NaiveDate,
+    first_date: NaiveDate,
+    last_date: NaiveDate,
+) -> String {
+    let clear_history = &daily_puzzle_info.clear_history;
+    let statistics = clear_history.month_statistics(month, first_date, last_date);
+    format!(
+        "Streak {} days (longest {})\n{}: {}/{} cleared ({}%), avg {}",
+        clear_history.current_streak(last_date),
+        clear_history.longest_streak(),
+        Month::from_u32(month.month()).unwrap().name(),
+        statistics.cleared,
+        statistics.total,
+        if statistics.total == 0 {
+            0
+        } else {
+            statistics.cleared * 100 / statistics.total
+        },
+        statistics
+            .average_time
+            .map_or("-".to_string(), duration_to_string),
+    )
+}
+
+/// Hover text of a date button in the calendar
+fn daily_record_to_string(date: NaiveDate, record: Option<&DailyRecord>) -> String {
+    let record = match record {
+        Some(record) => record,
+        None => return format!("{}\nNot played yet", date),
+    };
+    let best = match (record.best_time, record.best_moves) {
+        (Some(time), Some(moves)) => format!("Best {} / {} moves", duration_to_string(time), moves),
+        // cleared before records were kept
+        _ if record.cleared => "Cleared (no record)".to_string(),
+        _ => "Not cleared yet".to_string(),
+    };
+    let first_clear = record.first_clear.map_or("-".to_string(), |first_clear| {
+        first_clear.format("%Y-%m-%d %H:%M").to_string()
+    });
+    let attempts = match record.attempts {
+        0 => "-".to_string(),
+        attempts => attempts.to_string(),
+    };
+    format!(
+        "{}\n{}\nAttempts {}{}\nFirst clear {}",
+        date,
+        best,
+        attempts,
+        if record.undo_used { " (undo used)" } else { "" },
+        first_clear
+    )
 }
 
 fn spawn_calendar_ui(
@@ -361,14 +501,14 @@ fn spawn_calendar_ui(
             parent,
             UiRect {
                 top: Val::Px(0.0),
-                left: Val::Px(60.0 * i as f32),
+                left: Val::Px(CALENDAR_CELL_SIZE * i as f32),
                 ..default()
             },
-            Size::new(Val::Px(60.0), Val::Px(60.0)),
+            Size::new(Val::Px(CALENDAR_CELL_SIZE), Val::Px(CALENDAR_CELL_SIZE)),
             day_char.to_string(),
             font.clone(),
             Color::BLACK,
-            None,
+            None::<()>,
         );
     }
 
@@ -394,17 +534,39 @@ fn spawn_calendar_ui(
             break;
         }
         let clear = daily_puzzle_info.clear_history.get(date);
+        // record info opens above the button, toward the inside of the calendar
+        let weekday = date.weekday().num_days_from_sunday();
+        let info_position = if weekday < 4 {
+            UiRect {
+                left: Val::Px(0.0),
+                bottom: Val::Px(CALENDAR_CELL_SIZE + 5.0),
+                ..default()
+            }
+        } else {
+            UiRect {
+                right: Val::Px(0.0),
+                bottom: Val::Px(CALENDAR_CELL_SIZE + 5.0),
+                ..default()
+            }
+        };
         spawn_popup_date_selection_button(
             parent,
             UiRect {
-                left: Val::Px(60.0 * date.weekday().num_days_from_sunday() as f32),
-                top: Val::Px(60.0 * (1 + get_week(date) - get_week(calendar_first_date)) as f32),
+                left: Val::Px(CALENDAR_CELL_SIZE * weekday as f32),
+                top: Val::Px(
+                    CALENDAR_CELL_SIZE
+                        * (1 + get_week(date) - get_week(calendar_first_date)) as f32,
+                ),
                 ..default()
             },
             date.day().to_string(),
             font.clone(),
             PopupDateSelectionButtonType::Date(date, clear),
             if clear { BUTTON_GREEN } else { BUTTON_WHITE },
+            Some((
+                daily_record_to_string(date, daily_puzzle_info.clear_history.record(date)),
+                info_position,
+            )),
         );
     }
 }
@@ -1,11 +1,11 @@
 use crate::{
-    block::{spawn_meshes, BlockMesh},
-    game::{BoardSize, EasyMode, GameState, MAX_BOARD_SIZE, MIN_BOARD_SIZE},
-    local_storage::LocalStorage,
+    game::{MAX_BOARD_SIZE, MIN_BOARD_SIZE},
     player::PlayerState,
+    settings::Settings,
+    theme::ThemeColor,
     ui::*,
 };
-use bevy::{math::vec3, prelude::*};
+use bevy::prelude::*;
 
 #[derive(Component)]
 pub enum DifficultyButtonType {
@@ -13,11 +13,23 @@ pub enum DifficultyButtonType {
     SetEasyMode(bool),
 }
 
+/// Board size and difficulty chosen in the popup, applied to `Settings` on close
+#[derive(Resource)]
+pub struct DifficultySelection {
+    pub board_size: usize,
+    pub easy_mode: bool,
+}
+
 pub fn spawn_popup_difficulty(
     mut commands: Commands,
     mut game_ui_query: Query<Entity, With<GameUI>>,
     asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
 ) {
+    commands.insert_resource(DifficultySelection {
+        board_size: settings.board_size,
+        easy_mode: settings.easy_mode,
+    });
     let font = asset_server.load("fonts/Quicksand-Bold.ttf");
     let button_close_image = UiImage::from(asset_server.load("images/button_close.png"));
     let button_small_image = UiImage::from(asset_server.load("images/button_small.png"));
@@ -27,7 +39,7 @@ pub fn spawn_popup_difficulty(
         .with_children(|parent| {
             spawn_popup_panel(parent, button_close_image.clone(), font.clone(), |parent| {
                 // difficulty text
-                parent.spawn(
+                parent.spawn((
                     TextBundle::from_section(
                         "Difficulty",
                         TextStyle {
@@ -43,10 +55,11 @@ pub fn spawn_popup_difficulty(
                         },
                         ..default()
                     }),
-                );
+                    ThemeColor::Text,
+                ));
 
                 // board size
-                parent.spawn(
+                parent.spawn((
                     TextBundle::from_section(
                         "Board size",
                         TextStyle {
@@ -64,7 +77,8 @@ pub fn spawn_popup_difficulty(
                         },
                         ..default()
                     }),
-                );
+                    ThemeColor::Text,
+                ));
 
                 // board size button
                 for i in MIN_BOARD_SIZE..MAX_BOARD_SIZE + 1 {
@@ -83,7 +97,7 @@ pub fn spawn_popup_difficulty(
                 }
 
                 // difficulty
-                parent.spawn(
+                parent.spawn((
                     TextBundle::from_section(
                         "Difficulty",
                         TextStyle {
@@ -101,7 +115,8 @@ pub fn spawn_popup_difficulty(
                         },
                         ..default()
                     }),
-                );
+                    ThemeColor::Text,
+                ));
 
                 // difficulty button
                 spawn_small_button(
@@ -137,44 +152,40 @@ pub fn popup_system_difficulty(
         (&Interaction, &mut BackgroundColor, &DifficultyButtonType),
         (Changed<Interaction>, With<Button>),
     >,
+    mut selection: ResMut<DifficultySelection>,
+    settings: Res<Settings>,
 ) {
+    let button_color = settings.theme.ui.button_color();
     for (interaction, mut color, button_type) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
                 match button_type {
                     DifficultyButtonType::SetBoardSize(size) => {
-                        LocalStorage::set_board_size(&BoardSize(*size));
+                        selection.board_size = *size;
                     }
                     DifficultyButtonType::SetEasyMode(value) => {
-                        LocalStorage::set_easy_mode(&EasyMode(*value));
+                        selection.easy_mode = *value;
                     }
                 }
-                *color = (BUTTON_WHITE * BUTTON_PRESS_MUL).into();
+                *color = (button_color * BUTTON_PRESS_MUL).into();
             }
-            Interaction::Hovered => *color = (BUTTON_WHITE * BUTTON_HOVER_MUL).into(),
-            Interaction::None => *color = BUTTON_WHITE.into(),
+            Interaction::Hovered => *color = (button_color * BUTTON_HOVER_MUL).into(),
+            Interaction::None => *color = button_color.into(),
         }
     }
 }
 
 pub fn popup_difficulty_close_button_system(
-    mut commands: Commands,
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor, Option<&Popup>),
         (Changed<Interaction>, With<Button>, With<PopupCloseButton>),
     >,
     mut player_state: ResMut<State<PlayerState>>,
     keyboard_input: Res<Input<KeyCode>>,
-    mut board_size: ResMut<BoardSize>,
-    mut easy_mode: ResMut<EasyMode>,
-    mut game_query: Query<&mut GameState>,
-    block_mesh_query: Query<Entity, With<BlockMesh>>,
-    asset_server: Res<AssetServer>,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<StandardMaterial>>,
-    mut camera_query: Query<&mut Transform, With<Camera>>,
+    selection: Res<DifficultySelection>,
+    mut settings: ResMut<Settings>,
 ) {
-    let mut game = game_query.single_mut();
+    let button_color = settings.theme.ui.button_color();
     let mut close = false;
 
     for (interaction, mut color, popup) in &mut interaction_query {
@@ -183,9 +194,9 @@ pub fn popup_difficulty_close_button_system(
         }
         if popup.is_none() {
             *color = match *interaction {
-                Interaction::Clicked => (BUTTON_WHITE * BUTTON_PRESS_MUL).into(),
-                Interaction::Hovered => (BUTTON_WHITE * BUTTON_HOVER_MUL).into(),
-                Interaction::None => BUTTON_WHITE.into(),
+                Interaction::Clicked => (button_color * BUTTON_PRESS_MUL).into(),
+                Interaction::Hovered => (button_color * BUTTON_HOVER_MUL).into(),
+                Interaction::None => button_color.into(),
             };
         }
     }
@@ -196,40 +207,11 @@ pub fn popup_difficulty_close_button_system(
     }
 
     if close {
-        let mut change = false;
-        if let Some(BoardSize(size)) = LocalStorage::get_board_size() {
-            if board_size.0 != size {
-                board_size.0 = size;
-                change = true;
-            }
-        }
-        if let Some(EasyMode(value)) = LocalStorage::get_easy_mode() {
-            if easy_mode.0 != value {
-                easy_mode.0 = value;
-                change = true;
-            }
-        }
-
-        if change {
-            // spawn new meshes
-            block_mesh_query.for_each(|entity| commands.entity(entity).despawn());
-            let mesh_entities =
-                spawn_meshes(&mut commands, board_size.0, meshes, materials, asset_server);
-            *camera_query.single_mut() = Transform::from_xyz(
-                (board_size.0 as f32 - 1.0) / 2.0,
-                board_size.0 as f32 * 1.25,
-                (board_size.0 as f32 - 1.0) / 2.0 + board_size.0 as f32 * 1.25,
-            )
-            .looking_at(
-                vec3(
-                    (board_size.0 as f32 - 1.0) / 2.0,
-                    0.0,
-                    (board_size.0 as f32 - 1.0) / 2.0,
-                ),
-                Vec3::Y,
-            );
-            game.init(board_size.0, &mesh_entities);
-            player_state.replace(PlayerState::Idle).unwrap();
+        if settings.board_size != selection.board_size || settings.easy_mode != selection.easy_mode
+        {
+            // a new board is started by the settings change, which also closes the popup
+            settings.board_size = selection.board_size;
+            settings.easy_mode = selection.easy_mode;
         } else {
             player_state.pop().unwrap();
         }
@@ -1,4 +1,4 @@
-use crate::ui::*;
+use crate::{theme::ThemeColor, ui::*};
 use bevy::prelude::*;
 
 pub fn spawn_popup_enroll_score(
@@ -14,7 +14,7 @@ pub fn spawn_popup_enroll_score(
         .with_children(|parent| {
             spawn_popup_panel(parent, button_close_image.clone(), font.clone(), |parent| {
                 // enroll score text
-                parent.spawn(
+                parent.spawn((
                     TextBundle::from_section(
                         "Enroll Score",
                         TextStyle {
@@ -30,7 +30,8 @@ pub fn spawn_popup_enroll_score(
                         },
                         ..default()
                     }),
-                );
+                    ThemeColor::Text,
+                ));
 
                 // time, move text
                 parent.spawn((
@@ -52,10 +53,11 @@ pub fn spawn_popup_enroll_score(
                         ..default()
                     }),
                     MyTextType::PlayerInfo,
+                    ThemeColor::Text,
                 ));
 
                 // user name input guide text
-                parent.spawn(
+                parent.spawn((
                     TextBundle::from_section(
                         "Enter your name:",
                         TextStyle {
@@ -73,7 +75,8 @@ pub fn spawn_popup_enroll_score(
                         },
                         ..default()
                     }),
-                );
+                    ThemeColor::Hint,
+                ));
 
                 // user name text input box
                 spawn_text_input_box(
@@ -120,7 +123,8 @@ pub fn spawn_popup_enroll_score(
                         },
                         ..default()
                     }),
-                    MyTextType::EnrollDailyScoreResult,
+                    MyTextType::EnrollScoreResult,
+                    ThemeColor::Hint,
                 ));
             });
         });
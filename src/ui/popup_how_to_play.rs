@@ -1,8 +1,5 @@
-use std::collections::HashSet;
-
-use crate::{local_storage::LocalStorage, player::PlayerState, ui::*};
+use crate::{player::PlayerState, settings::Settings, ui::*};
 use bevy::prelude::*;
-use serde::{Deserialize, Serialize};
 
 #[derive(Resource, Default, Debug)]
 pub struct GameModeChange(Option<GameMode>);
@@ -10,9 +7,6 @@ pub struct GameModeChange(Option<GameMode>);
 #[derive(Component)]
 pub struct HowToPlayPopup;
 
-#[derive(Serialize, Deserialize, Default)]
-pub struct SkipHowToPlay(HashSet<GameMode>);
-
 #[derive(Component)]
 struct SkipHowToPlayButton;
 
@@ -55,13 +49,12 @@ fn try_spawn_how_to_play(
     mut game_mode_change: ResMut<GameModeChange>,
     mut player_state: ResMut<State<PlayerState>>,
     asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
 ) {
     if let Some(game_mode) = game_mode_change.0 {
         // skip
-        if let Some(skip_how_to_play) = LocalStorage::get_skip_how_to_play() {
-            if skip_how_to_play.0.contains(&game_mode) {
-                return;
-            }
+        if settings.skip_how_to_play.contains(&game_mode) {
+            return;
         }
 
         // skip if state is PlayerState::ResponseWaiting
@@ -143,24 +136,24 @@ fn how_to_play_system(
     mut player_state: ResMut<State<PlayerState>>,
     game_mode: Res<State<GameMode>>,
     asset_server: Res<AssetServer>,
+    mut settings: ResMut<Settings>,
 ) {
+    let button_color = settings.theme.ui.button_color();
     for (interaction, mut color, mut image) in skip_button_interaction.iter_mut() {
         match *interaction {
             Interaction::Clicked => {
                 let game_mode = game_mode.current();
-                let mut skip_how_to_play = LocalStorage::get_skip_how_to_play().unwrap_or_default();
-                if skip_how_to_play.0.contains(game_mode) {
-                    skip_how_to_play.0.remove(game_mode);
+                if settings.skip_how_to_play.contains(game_mode) {
+                    settings.skip_how_to_play.remove(game_mode);
                     image.0 = asset_server.load("images/button_toggle_off.png");
                 } else {
-                    skip_how_to_play.0.insert(*game_mode);
+                    settings.skip_how_to_play.insert(*game_mode);
                     image.0 = asset_server.load("images/button_toggle_on.png");
                 }
-                LocalStorage::set_skip_how_to_play(&skip_how_to_play);
-                *color = (BUTTON_WHITE * BUTTON_PRESS_MUL).into();
+                *color = (button_color * BUTTON_PRESS_MUL).into();
             }
-            Interaction::Hovered => *color = (BUTTON_WHITE * BUTTON_HOVER_MUL).into(),
-            Interaction::None => *color = BUTTON_WHITE.into(),
+            Interaction::Hovered => *color = (button_color * BUTTON_HOVER_MUL).into(),
+            Interaction::None => *color = button_color.into(),
         }
     }
     for interaction in popup_close_interaction.iter() {
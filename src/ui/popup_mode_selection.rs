@@ -1,7 +1,9 @@
 use crate::{
-    game::{BoardSize, EasyMode, GameState, MoveTimer, INITIAL_BOARD_SIZE},
+    game::{GameState, MoveTimer, INITIAL_BOARD_SIZE},
     network::NetworkChannel,
     player::{PlayLog, PlayerInfo, PlayerState},
+    settings::Settings,
+    theme::ThemeColor,
     ui::*,
 };
 
@@ -19,7 +21,7 @@ pub fn spawn_popup_mode_selection(
         .with_children(|parent| {
             spawn_popup_panel(parent, button_close_image.clone(), font.clone(), |parent| {
                 // mode selection text
-                parent.spawn(
+                parent.spawn((
                     TextBundle::from_section(
                         "Mode Selection",
                         TextStyle {
@@ -35,7 +37,8 @@ pub fn spawn_popup_mode_selection(
                         },
                         ..default()
                     }),
-                );
+                    ThemeColor::Text,
+                ));
 
                 // practice mode button
                 spawn_button_and_description(
@@ -97,15 +100,15 @@ pub fn popup_system_mode_selection(
     mut move_timer: ResMut<MoveTimer>,
     mut game_query: Query<&mut GameState>,
     mut network_channel: Res<NetworkChannel>,
-    board_size: Res<BoardSize>,
-    easy_mode: Res<EasyMode>,
+    settings: Res<Settings>,
 ) {
+    let button_color = settings.theme.ui.button_color();
     for (interaction, mut color, button_type) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
                 if button_type != game_mode.current() {
                     if *button_type == GameMode::DailyPuzzle
-                        && (board_size.0 != INITIAL_BOARD_SIZE || easy_mode.0)
+                        && (settings.board_size != INITIAL_BOARD_SIZE || settings.easy_mode)
                     {
                         continue;
                     }
@@ -123,10 +126,10 @@ pub fn popup_system_mode_selection(
                 } else {
                     player_state.pop().unwrap();
                 }
-                *color = (BUTTON_WHITE * BUTTON_PRESS_MUL).into();
+                *color = (button_color * BUTTON_PRESS_MUL).into();
             }
-            Interaction::Hovered => *color = (BUTTON_WHITE * BUTTON_HOVER_MUL).into(),
-            Interaction::None => *color = BUTTON_WHITE.into(),
+            Interaction::Hovered => *color = (button_color * BUTTON_HOVER_MUL).into(),
+            Interaction::None => *color = button_color.into(),
         }
     }
 }
@@ -181,7 +184,7 @@ fn spawn_button_and_description(
         });
 
     // description text
-    parent.spawn(
+    parent.spawn((
         TextBundle::from_section(
             description_text,
             TextStyle {
@@ -202,5 +205,6 @@ fn spawn_button_and_description(
             size: Size::new(Val::Px(325.0), Val::Px(100.0)),
             ..default()
         }),
-    );
+        ThemeColor::Text,
+    ));
 }
@@ -1,17 +1,41 @@
 use crate::{
     daily_puzzle_info::DailyPuzzleInfo,
-    network::{Network, NetworkChannel},
-    player::PlayerState,
+    game::GameState,
+    network::{LeaderboardKey, Network, NetworkChannel, RankingKind},
+    player::{PlayerIdentity, PlayerState},
+    settings::Settings,
+    theme::ThemeColor,
     ui::*,
 };
 use bevy::prelude::*;
 
-const SCROLL_BAR_MAX_ITEMS: usize = 10;
+const SCROLL_BAR_MAX_ITEMS: usize = 8;
+pub const RANKING_PAGE_SIZE: usize = 50;
+const MY_RANK_NEIGHBORS: usize = 20;
 
-#[derive(Component, PartialEq, Eq)]
+#[derive(Component, PartialEq, Eq, Clone, Copy)]
 pub enum RankingType {
     Time,
     Move,
+    Composite,
+    AllTime,
+    Weekly,
+}
+
+#[derive(Component, PartialEq, Eq, Clone, Copy)]
+pub enum RankingPageButton {
+    Prev,
+    Next,
+    MyRank,
+}
+
+/// Which part of the ranking a ranking scroll bar is showing
+#[derive(Component)]
+pub struct RankingPageInfo {
+    pub kind: RankingKind,
+    pub offset: usize,
+    pub len: usize, // entries shown, a page around my rank is longer than `RANKING_PAGE_SIZE`
+    pub total: usize,
 }
 
 pub fn spawn_popup_rankings(
@@ -21,20 +45,68 @@ pub fn spawn_popup_rankings(
     daily_puzzle_info_query: Query<&DailyPuzzleInfo>,
     mut player_state: ResMut<State<PlayerState>>,
     network_channel: Res<NetworkChannel>,
+    game_mode: Res<State<GameMode>>,
+    game_query: Query<&GameState>,
+    settings: Res<Settings>,
 ) {
     let font = asset_server.load("fonts/Quicksand-Bold.ttf");
     let button_close_image = UiImage::from(asset_server.load("images/button_close.png"));
     let button_small_image = UiImage::from(asset_server.load("images/button_small.png"));
     let daily_puzzle_info = daily_puzzle_info_query.single();
 
+    // daily puzzle rankings, or leaderboard of current board size and difficulty
+    let leaderboard_key = game_mode
+        .current()
+        .leaderboard_mode()
+        .map(|mode| LeaderboardKey {
+            mode,
+            size: game_query.single().size,
+            easy_mode: settings.easy_mode,
+        });
+    let (title, tabs) = match leaderboard_key {
+        Some(key) => (
+            format!(
+                "{} Rankings {}x{} {}",
+                match game_mode.current() {
+                    GameMode::TimeAttack => "Time Attack",
+                    _ => "Min Move",
+                },
+                key.size,
+                key.size,
+                if key.easy_mode { "Easy" } else { "Hard" }
+            ),
+            vec![
+                (RankingType::AllTime, "All-time", RankingKind::AllTime(key)),
+                (RankingType::Weekly, "Weekly", RankingKind::Weekly(key)),
+            ],
+        ),
+        None => {
+            let date = daily_puzzle_info.current_date;
+            (
+                "Daily Puzzle Rankings".to_string(),
+                vec![
+                    (RankingType::Time, "Time", RankingKind::DailyTime(date)),
+                    (RankingType::Move, "Move", RankingKind::DailyMove(date)),
+                    (
+                        RankingType::Composite,
+                        "Overall",
+                        RankingKind::DailyComposite(date),
+                    ),
+                ],
+            )
+        }
+    };
+
+    let first_kind = tabs[0].2;
+
     commands
         .entity(game_ui_query.single_mut())
         .with_children(|parent| {
             spawn_popup_panel(parent, button_close_image.clone(), font.clone(), |parent| {
-                // daily puzzle rankings text
-                parent.spawn(
+                // rankings title text
+                parent.spawn((
                     TextBundle::from_section(
-                        "Daily Puzzle Rankings",
+                        title,
                         TextStyle {
                             font: font.clone(),
                             font_size: TEXT_SIZE,
@@ -48,78 +120,133 @@ pub fn spawn_popup_rankings(
                         },
                         ..default()
                     }),
-                );
+                    ThemeColor::Text,
+                ));
+
+                // tab buttons are centered, 150px wide with 20px gap
+                let tabs_left = (600.0 - 170.0 * tabs.len() as f32 + 20.0) / 2.0;
+                for (i, &(ranking_type, text, kind)) in tabs.iter().enumerate() {
+                    // ranking type select button
+                    spawn_ranking_button(
+                        parent,
+                        UiRect {
+                            left: Val::Px(tabs_left + 170.0 * i as f32),
+                            bottom: Val::Px(455.0),
+                            ..default()
+                        },
+                        Size::new(Val::Px(150.0), Val::Px(50.0)),
+                        button_small_image.clone(),
+                        ranking_type,
+                        text.to_string(),
+                        font.clone(),
+                    );
 
-                // time/move select button
-                spawn_ranking_type_button(
+                    // scroll bar background
+                    spawn_scroll_bar(
+                        parent,
+                        Size::new(Val::Px(550.0), Val::Px(340.0)),
+                        UiRect {
+                            left: Val::Px(25.0),
+                            bottom: Val::Px(95.0),
+                            ..default()
+                        },
+                        vec![],
+                        SCROLL_BAR_MAX_ITEMS,
+                        font.clone(),
+                        i == 0,
+                        Some((
+                            ranking_type,
+                            RankingPageInfo {
+                                kind,
+                                offset: 0,
+                                len: 0,
+                                total: 0,
+                            },
+                        )),
+                    );
+                }
+
+                // page buttons
+                spawn_ranking_button(
                     parent,
                     UiRect {
-                        left: Val::Px(140.0),
-                        bottom: Val::Px(455.0),
+                        left: Val::Px(25.0),
+                        bottom: Val::Px(30.0),
                         ..default()
                     },
+                    Size::new(Val::Px(100.0), Val::Px(50.0)),
                     button_small_image.clone(),
-                    RankingType::Time,
-                    "Time".to_string(),
+                    RankingPageButton::Prev,
+                    "<".to_string(),
                     font.clone(),
                 );
-                spawn_ranking_type_button(
+                spawn_ranking_button(
                     parent,
                     UiRect {
-                        right: Val::Px(140.0),
-                        bottom: Val::Px(455.0),
+                        left: Val::Px(135.0),
+                        bottom: Val::Px(30.0),
                         ..default()
                     },
+                    Size::new(Val::Px(100.0), Val::Px(50.0)),
                     button_small_image.clone(),
-                    RankingType::Move,
-                    "Move".to_string(),
+                    RankingPageButton::Next,
+                    ">".to_string(),
                     font.clone(),
                 );
-
-                // scroll bar background
-                spawn_scroll_bar(
+                spawn_ranking_button(
                     parent,
-                    Size::new(Val::Px(550.0), Val::Px(410.0)),
                     UiRect {
-                        left: Val::Px(25.0),
-                        bottom: Val::Px(25.0),
+                        right: Val::Px(25.0),
+                        bottom: Val::Px(30.0),
                         ..default()
                     },
-                    vec![],
-                    SCROLL_BAR_MAX_ITEMS,
+                    Size::new(Val::Px(150.0), Val::Px(50.0)),
+                    button_small_image.clone(),
+                    RankingPageButton::MyRank,
+                    "My Rank".to_string(),
                     font.clone(),
-                    true,
-                    Some(RankingType::Time),
                 );
-                spawn_scroll_bar(
-                    parent,
-                    Size::new(Val::Px(550.0), Val::Px(410.0)),
-                    UiRect {
-                        left: Val::Px(25.0),
-                        bottom: Val::Px(25.0),
+
+                // page text
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: TEXT_SIZE,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: Val::Px(250.0),
+                            bottom: Val::Px(35.0),
+                            ..default()
+                        },
                         ..default()
-                    },
-                    vec![],
-                    SCROLL_BAR_MAX_ITEMS,
-                    font.clone(),
-                    false,
-                    Some(RankingType::Move),
-                );
+                    }),
+                    MyTextType::RankingPage,
+                    ThemeColor::Text,
+                ));
             });
         });
 
-    Network::get_daily_ranking(
-        daily_puzzle_info.current_date,
+    Network::get_ranking_page(
+        first_kind,
+        0,
+        RANKING_PAGE_SIZE,
         &mut player_state,
         &network_channel,
     );
 }
 
-fn spawn_ranking_type_button(
+fn spawn_ranking_button(
     parent: &mut ChildBuilder,
     position: UiRect,
+    size: Size,
     image: UiImage,
-    ranking_type: RankingType,
+    button_type: impl Component,
     text: String,
     font: Handle<Font>,
 ) {
@@ -131,13 +258,13 @@ fn spawn_ranking_type_button(
                     justify_content: JustifyContent::Center,
                     position_type: PositionType::Absolute,
                     position,
-                    size: Size::new(Val::Px(150.0), Val::Px(50.0)),
+                    size,
                     ..default()
                 },
                 image,
                 ..default()
             },
-            ranking_type,
+            button_type,
         ))
         .with_children(|parent| {
             parent.spawn(
@@ -159,18 +286,84 @@ pub fn popup_rankings_system(
         (&Interaction, &mut BackgroundColor, &RankingType),
         (Changed<Interaction>, With<Button>),
     >,
-    mut scroll_bar_query: Query<(&mut Visibility, &RankingType), With<ScrollBar>>,
+    mut page_button_query: Query<
+        (&Interaction, &mut BackgroundColor, &RankingPageButton),
+        (Changed<Interaction>, With<Button>, Without<RankingType>),
+    >,
+    mut scroll_bar_query: Query<(&mut Visibility, &RankingType, &RankingPageInfo), With<ScrollBar>>,
+    mut player_state: ResMut<State<PlayerState>>,
+    network_channel: Res<NetworkChannel>,
+    player_identity: Res<PlayerIdentity>,
+    settings: Res<Settings>,
 ) {
+    let button_color = settings.theme.ui.button_color();
     for (interaction, mut color, button_type) in interaction_query.iter_mut() {
         match interaction {
             Interaction::Clicked => {
-                for (mut visibility, scroll_bar_type) in scroll_bar_query.iter_mut() {
+                for (mut visibility, scroll_bar_type, page_info) in scroll_bar_query.iter_mut() {
                     visibility.is_visible = *button_type == *scroll_bar_type;
+                    if visibility.is_visible {
+                        Network::get_ranking_page(
+                            page_info.kind,
+                            page_info.offset,
+                            RANKING_PAGE_SIZE,
+                            &mut player_state,
+                            &network_channel,
+                        );
+                    }
+                }
+                *color = (button_color * BUTTON_PRESS_MUL).into();
+            }
+            Interaction::Hovered => *color = (button_color * BUTTON_HOVER_MUL).into(),
+            Interaction::None => *color = button_color.into(),
+        }
+    }
+
+    for (interaction, mut color, button_type) in page_button_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                let page_info = scroll_bar_query
+                    .iter()
+                    .find(|(visibility, _, _)| visibility.is_visible)
+                    .map(|(_, _, page_info)| page_info);
+                if let Some(page_info) = page_info {
+                    match button_type {
+                        RankingPageButton::Prev if page_info.offset > 0 => {
+                            Network::get_ranking_page(
+                                page_info.kind,
+                                page_info.offset.saturating_sub(RANKING_PAGE_SIZE),
+                                RANKING_PAGE_SIZE,
+                                &mut player_state,
+                                &network_channel,
+                            );
+                        }
+                        RankingPageButton::Next
+                            if page_info.offset + page_info.len < page_info.total =>
+                        {
+                            Network::get_ranking_page(
+                                page_info.kind,
+                                page_info.offset + page_info.len,
+                                RANKING_PAGE_SIZE,
+                                &mut player_state,
+                                &network_channel,
+                            );
+                        }
+                        RankingPageButton::MyRank => {
+                            Network::get_my_rank(
+                                page_info.kind,
+                                player_identity.0.id.clone(),
+                                MY_RANK_NEIGHBORS,
+                                &mut player_state,
+                                &network_channel,
+                            );
+                        }
+                        _ => {}
+                    }
                 }
-                *color = (BUTTON_WHITE * BUTTON_PRESS_MUL).into();
+                *color = (button_color * BUTTON_PRESS_MUL).into();
             }
-            Interaction::Hovered => *color = (BUTTON_WHITE * BUTTON_HOVER_MUL).into(),
-            Interaction::None => *color = BUTTON_WHITE.into(),
+            Interaction::Hovered => *color = (button_color * BUTTON_HOVER_MUL).into(),
+            Interaction::None => *color = button_color.into(),
         }
     }
 }
@@ -1,21 +1,189 @@
 use bevy::prelude::*;
 
 use crate::{
-    buffered_input::{InputInversionFlag, MoveImmediate},
+    buffered_input::{InputAction, KeyBindings},
+    settings::{
+        Settings, MAX_INPUT_BUFFER_SIZE, MAX_REPEAT_FILTER, MAX_SWIPE_THRESHOLD,
+        MIN_INPUT_BUFFER_SIZE, MIN_SWIPE_THRESHOLD,
+    },
+    theme::{cycle, Environment, Palette, SceneTextColor, ThemeColor, UiTheme},
     ui::*,
 };
 
+const KEY_BINDING_TEXT_SIZE: f32 = 30.0;
+const SWIPE_THRESHOLD_STEP: f32 = 10.0;
+const REPEAT_FILTER_STEP: f32 = 0.01;
+
+/// Views of the settings popup, cycled by `SettingsViewButton`
+#[derive(Component, PartialEq, Eq, Clone, Copy)]
+pub enum SettingsView {
+    General,
+    Controls,
+    Input,
+    Theme,
+}
+
+impl SettingsView {
+    fn next(&self) -> Self {
+        match self {
+            SettingsView::General => SettingsView::Controls,
+            SettingsView::Controls => SettingsView::Input,
+            SettingsView::Input => SettingsView::Theme,
+            SettingsView::Theme => SettingsView::General,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SettingsView::General => "General",
+            SettingsView::Controls => "Controls",
+            SettingsView::Input => "Input",
+            SettingsView::Theme => "Theme",
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct SettingsViewButton;
+
+#[derive(Component, Clone, Copy)]
+pub enum KeyBindingButton {
+    Add(InputAction),
+    Remove(InputAction),
+    Reset,
+}
+
+#[derive(Component)]
+pub struct KeyBindingText(InputAction);
+
+#[derive(Component)]
+pub struct KeyBindingStatusText;
+
+/// Settings changed by steps, numbers or choices of the theme
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum StepSetting {
+    SwipeThreshold,
+    InputBufferSize,
+    RepeatFilter,
+    Palette,
+    UiTheme,
+    SceneTextColor,
+    Environment,
+}
+
+impl StepSetting {
+    const INPUT: [StepSetting; 3] = [
+        StepSetting::SwipeThreshold,
+        StepSetting::InputBufferSize,
+        StepSetting::RepeatFilter,
+    ];
+    const THEME: [StepSetting; 4] = [
+        StepSetting::Palette,
+        StepSetting::UiTheme,
+        StepSetting::SceneTextColor,
+        StepSetting::Environment,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            StepSetting::SwipeThreshold => "Swipe",
+            StepSetting::InputBufferSize => "Buffered inputs",
+            StepSetting::RepeatFilter => "Repeat filter",
+            StepSetting::Palette => "Colors",
+            StepSetting::UiTheme => "UI",
+            StepSetting::SceneTextColor => "Scene text",
+            StepSetting::Environment => "Scene",
+        }
+    }
+
+    /// choices are stepped through with arrows instead of plus and minus
+    fn is_choice(&self) -> bool {
+        matches!(
+            self,
+            StepSetting::Palette
+                | StepSetting::UiTheme
+                | StepSetting::SceneTextColor
+                | StepSetting::Environment
+        )
+    }
+
+    fn value_to_string(&self, settings: &Settings) -> String {
+        match self {
+            StepSetting::SwipeThreshold => format!("{} px / cell", settings.swipe_threshold),
+            StepSetting::InputBufferSize => settings.input_buffer_size.to_string(),
+            StepSetting::RepeatFilter if settings.repeat_filter <= 0.0 => "Off".to_string(),
+            StepSetting::RepeatFilter => format!("{:.2} s", settings.repeat_filter),
+            StepSetting::Palette => settings.theme.palette.name().to_string(),
+            StepSetting::UiTheme => settings.theme.ui.name().to_string(),
+            StepSetting::SceneTextColor => settings.theme.scene_text.name().to_string(),
+            StepSetting::Environment => settings.theme.environment.name().to_string(),
+        }
+    }
+
+    fn step(&self, settings: &mut Settings, increase: bool) {
+        let sign = if increase { 1.0 } else { -1.0 };
+        match self {
+            StepSetting::SwipeThreshold => {
+                settings.swipe_threshold = (settings.swipe_threshold + sign * SWIPE_THRESHOLD_STEP)
+                    .clamp(MIN_SWIPE_THRESHOLD, MAX_SWIPE_THRESHOLD);
+            }
+            StepSetting::InputBufferSize => {
+                settings.input_buffer_size = match increase {
+                    true => settings.input_buffer_size + 1,
+                    false => settings.input_buffer_size.saturating_sub(1),
+                }
+                .clamp(MIN_INPUT_BUFFER_SIZE, MAX_INPUT_BUFFER_SIZE);
+            }
+            StepSetting::RepeatFilter => {
+                // rounded to the step, as float steps drift
+                let value = settings.repeat_filter + sign * REPEAT_FILTER_STEP;
+                settings.repeat_filter = ((value / REPEAT_FILTER_STEP).round()
+                    * REPEAT_FILTER_STEP)
+                    .clamp(0.0, MAX_REPEAT_FILTER);
+            }
+            StepSetting::Palette => {
+                settings.theme.palette = cycle(&Palette::ALL, settings.theme.palette, increase);
+            }
+            StepSetting::UiTheme => {
+                settings.theme.ui = cycle(&UiTheme::ALL, settings.theme.ui, increase);
+            }
+            StepSetting::SceneTextColor => {
+                settings.theme.scene_text =
+                    cycle(&SceneTextColor::ALL, settings.theme.scene_text, increase);
+            }
+            StepSetting::Environment => {
+                settings.theme.environment =
+                    cycle(&Environment::ALL, settings.theme.environment, increase);
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct StepSettingButton {
+    setting: StepSetting,
+    increase: bool,
+}
+
+#[derive(Component)]
+pub struct StepSettingText(StepSetting);
+
+/// Action waiting for the next key press to be bound
+#[derive(Resource, Default)]
+pub struct KeyCapture(Option<InputAction>);
+
 use super::spawn_popup_panel;
 
 pub fn spawn_popup_settings(
     mut commands: Commands,
     game_ui_query: Query<Entity, With<GameUI>>,
     asset_server: Res<AssetServer>,
-    input_system: Query<(&InputInversionFlag, &MoveImmediate)>,
+    settings: Res<Settings>,
     game_mode: Res<State<GameMode>>,
 ) {
+    commands.insert_resource(KeyCapture::default());
     let font = asset_server.load("fonts/Quicksand-Bold.ttf");
-    let (input_inversion, move_immediate) = input_system.single();
+    let button_small_image = UiImage::from(asset_server.load("images/button_small.png"));
     let button_close_image = UiImage::from(asset_server.load("images/button_close.png"));
     let button_toggle_on_image = UiImage::from(asset_server.load("images/button_toggle_on.png"));
     let button_toggle_off_image = UiImage::from(asset_server.load("images/button_toggle_off.png"));
@@ -25,7 +193,7 @@ pub fn spawn_popup_settings(
         .with_children(|parent| {
             spawn_popup_panel(parent, button_close_image.clone(), font.clone(), |parent| {
                 // settings text
-                parent.spawn(
+                parent.spawn((
                     TextBundle::from_section(
                         "Settings",
                         TextStyle {
@@ -41,143 +209,476 @@ pub fn spawn_popup_settings(
                         },
                         ..default()
                     }),
-                );
+                    ThemeColor::Text,
+                ));
 
-                // animation toggle button
-                spawn_toggle_button(
+                // view select button
+                spawn_small_button(
                     parent,
                     UiRect {
-                        right: Val::Px(185.0),
-                        top: Val::Px(140.0),
+                        left: Val::Px(25.0),
+                        top: Val::Px(25.0),
                         ..default()
                     },
-                    "Animation".to_string(),
+                    Size::new(Val::Px(130.0), Val::Px(45.0)),
+                    button_small_image.clone(),
+                    SettingsViewButton,
+                    "Controls".to_string(),
                     font.clone(),
-                    MyButtonType::AnimationToggle,
-                    match move_immediate.0 {
-                        true => button_toggle_off_image.clone(),
-                        false => button_toggle_on_image.clone(),
-                    },
                 );
 
-                // input inversion button
-                spawn_toggle_button(
-                    parent,
-                    UiRect {
-                        right: Val::Px(185.0),
-                        top: Val::Px(210.0),
-                        ..default()
-                    },
-                    "Input Inversion".to_string(),
-                    font.clone(),
-                    MyButtonType::InputInversion,
-                    match input_inversion.0 {
-                        true => button_toggle_on_image.clone(),
-                        false => button_toggle_off_image.clone(),
-                    },
-                );
+                // general settings
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        SettingsView::General,
+                    ))
+                    .with_children(|parent| {
+                        // animation toggle button
+                        spawn_toggle_button(
+                            parent,
+                            UiRect {
+                                right: Val::Px(185.0),
+                                top: Val::Px(90.0),
+                                ..default()
+                            },
+                            "Animation".to_string(),
+                            font.clone(),
+                            MyButtonType::AnimationToggle,
+                            match settings.move_immediate {
+                                true => button_toggle_off_image.clone(),
+                                false => button_toggle_on_image.clone(),
+                            },
+                        );
 
-                // share button
-                spawn_image_button(
-                    parent,
-                    UiRect {
-                        left: Val::Px(50.0),
-                        bottom: Val::Px(50.0),
-                        ..default()
-                    },
-                    MyButtonType::Share,
-                    asset_server.load("images/button_share.png").into(),
-                    "Share URL of current game".to_string(),
-                    font.clone(),
-                );
+                        // input inversion button
+                        spawn_toggle_button(
+                            parent,
+                            UiRect {
+                                right: Val::Px(185.0),
+                                top: Val::Px(140.0),
+                                ..default()
+                            },
+                            "Input Inversion".to_string(),
+                            font.clone(),
+                            MyButtonType::InputInversion,
+                            match settings.input_inversion {
+                                true => button_toggle_on_image.clone(),
+                                false => button_toggle_off_image.clone(),
+                            },
+                        );
 
-                // share result UI
-                parent.spawn((
-                    TextBundle::from_sections([
-                        TextSection::new(
-                            "",
-                            TextStyle {
-                                font: font.clone(),
-                                font_size: TEXT_SIZE,
-                                color: Color::GRAY,
-                            },
-                        ),
-                        TextSection::new(
-                            "",
-                            TextStyle {
-                                font: font.clone(),
-                                font_size: TEXT_SIZE * 0.7,
-                                color: Color::GRAY,
-                            },
-                        ),
-                    ])
-                    .with_text_alignment(TextAlignment::CENTER_LEFT)
-                    .with_style(Style {
-                        position_type: PositionType::Absolute,
-                        position: UiRect {
-                            left: Val::Px(170.0),
-                            bottom: Val::Px(68.0),
+                        // player link button: moves player identity to another device
+                        spawn_toggle_button(
+                            parent,
+                            UiRect {
+                                right: Val::Px(185.0),
+                                top: Val::Px(190.0),
+                                ..default()
+                            },
+                            "Copy Player Link".to_string(),
+                            font.clone(),
+                            MyButtonType::CopyPlayerLink,
+                            asset_server.load("images/button_share.png").into(),
+                        );
+
+                        // settings export and import buttons: move settings to another device
+                        spawn_toggle_button(
+                            parent,
+                            UiRect {
+                                right: Val::Px(185.0),
+                                top: Val::Px(240.0),
+                                ..default()
+                            },
+                            "Copy Settings".to_string(),
+                            font.clone(),
+                            MyButtonType::ExportSettings,
+                            asset_server.load("images/button_share.png").into(),
+                        );
+                        spawn_toggle_button(
+                            parent,
+                            UiRect {
+                                right: Val::Px(185.0),
+                                top: Val::Px(290.0),
+                                ..default()
+                            },
+                            "Paste Settings".to_string(),
+                            font.clone(),
+                            MyButtonType::ImportSettings,
+                            asset_server.load("images/button_load.png").into(),
+                        );
+
+                        // share button
+                        spawn_image_button(
+                            parent,
+                            UiRect {
+                                left: Val::Px(50.0),
+                                bottom: Val::Px(50.0),
+                                ..default()
+                            },
+                            MyButtonType::Share,
+                            asset_server.load("images/button_share.png").into(),
+                            "Share URL of current game".to_string(),
+                            font.clone(),
+                        );
+
+                        // share result UI
+                        parent.spawn((
+                            TextBundle::from_sections([
+                                TextSection::new(
+                                    "",
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: TEXT_SIZE,
+                                        color: Color::GRAY,
+                                    },
+                                ),
+                                TextSection::new(
+                                    "",
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: TEXT_SIZE * 0.7,
+                                        color: Color::GRAY,
+                                    },
+                                ),
+                            ])
+                            .with_text_alignment(TextAlignment::CENTER_LEFT)
+                            .with_style(Style {
+                                position_type: PositionType::Absolute,
+                                position: UiRect {
+                                    left: Val::Px(170.0),
+                                    bottom: Val::Px(68.0),
+                                    ..default()
+                                },
+                                ..default()
+                            }),
+                            MyTextType::ShareURL,
+                            ThemeColor::Hint,
+                        ));
+
+                        // load URL UI: practice mode only
+                        if *game_mode.current() == GameMode::Practice {
+                            // load URL button
+                            spawn_image_button(
+                                parent,
+                                UiRect {
+                                    bottom: Val::Px(170.0),
+                                    left: Val::Px(50.0),
+                                    ..default()
+                                },
+                                MyButtonType::LoadURL,
+                                asset_server.load("images/button_load.png").into(),
+                                "Load URL".to_string(),
+                                font.clone(),
+                            );
+
+                            // load URL guide text
+                            parent.spawn((
+                                TextBundle::from_section(
+                                    "Enter URL (after \"/?\"):",
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: TEXT_SIZE,
+                                        color: Color::GRAY,
+                                    },
+                                )
+                                .with_style(Style {
+                                    position_type: PositionType::Absolute,
+                                    position: UiRect {
+                                        bottom: Val::Px(225.0),
+                                        left: Val::Px(170.0),
+                                        ..default()
+                                    },
+                                    ..default()
+                                }),
+                                ThemeColor::Hint,
+                            ));
+
+                            // load URL text
+                            spawn_text_input_box(
+                                parent,
+                                UiRect {
+                                    bottom: Val::Px(175.0),
+                                    left: Val::Px(170.0),
+                                    ..default()
+                                },
+                                Size::new(Val::Px(380.0), Val::Px(40.0)),
+                                font.clone(),
+                                12,
+                            );
+                        }
+                    });
+
+                // key bindings
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            visibility: Visibility::INVISIBLE,
                             ..default()
                         },
-                        ..default()
-                    }),
-                    MyTextType::ShareURL,
-                ));
+                        SettingsView::Controls,
+                    ))
+                    .with_children(|parent| {
+                        for (i, action) in InputAction::ALL.into_iter().enumerate() {
+                            let top = 80.0 + 55.0 * i as f32;
+
+                            // action name and bound keys
+                            for (text, left, text_type) in [
+                                (action.name().to_string(), 40.0, None),
+                                (
+                                    settings.key_bindings.keys_to_string(action),
+                                    140.0,
+                                    Some(KeyBindingText(action)),
+                                ),
+                            ] {
+                                let bundle = TextBundle::from_section(
+                                    text,
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: KEY_BINDING_TEXT_SIZE,
+                                        color: Color::WHITE,
+                                    },
+                                )
+                                .with_style(Style {
+                                    position_type: PositionType::Absolute,
+                                    position: UiRect {
+                                        left: Val::Px(left),
+                                        top: Val::Px(top + 5.0),
+                                        ..default()
+                                    },
+                                    ..default()
+                                });
+                                match text_type {
+                                    Some(text_type) => {
+                                        parent.spawn((bundle, text_type, ThemeColor::Text))
+                                    }
+                                    None => parent.spawn((bundle, ThemeColor::Text)),
+                                };
+                            }
 
-                // load URL UI: practice mode only
-                if *game_mode.current() == GameMode::Practice {
-                    // load URL button
-                    spawn_image_button(
-                        parent,
-                        UiRect {
-                            bottom: Val::Px(170.0),
-                            left: Val::Px(50.0),
+                            // add and remove binding buttons
+                            for (button_type, text, right) in [
+                                (KeyBindingButton::Add(action), "+", 95.0),
+                                (KeyBindingButton::Remove(action), "-", 40.0),
+                            ] {
+                                spawn_small_button(
+                                    parent,
+                                    UiRect {
+                                        right: Val::Px(right),
+                                        top: Val::Px(top),
+                                        ..default()
+                                    },
+                                    Size::new(Val::Px(45.0), Val::Px(45.0)),
+                                    button_small_image.clone(),
+                                    button_type,
+                                    text.to_string(),
+                                    font.clone(),
+                                );
+                            }
+                        }
+
+                        // rebinding guide and conflicts
+                        parent.spawn((
+                            TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: KEY_BINDING_TEXT_SIZE,
+                                    color: Color::GRAY,
+                                },
+                            )
+                            .with_style(Style {
+                                position_type: PositionType::Absolute,
+                                position: UiRect {
+                                    left: Val::Px(40.0),
+                                    top: Val::Px(420.0),
+                                    ..default()
+                                },
+                                ..default()
+                            }),
+                            KeyBindingStatusText,
+                            ThemeColor::Hint,
+                        ));
+
+                        // reset button
+                        spawn_small_button(
+                            parent,
+                            UiRect {
+                                right: Val::Px(40.0),
+                                bottom: Val::Px(30.0),
+                                ..default()
+                            },
+                            Size::new(Val::Px(150.0), Val::Px(50.0)),
+                            button_small_image.clone(),
+                            KeyBindingButton::Reset,
+                            "Reset".to_string(),
+                            font.clone(),
+                        );
+                    });
+
+                // input handling
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            visibility: Visibility::INVISIBLE,
                             ..default()
                         },
-                        MyButtonType::LoadURL,
-                        asset_server.load("images/button_load.png").into(),
-                        "Load URL".to_string(),
-                        font.clone(),
-                    );
-
-                    // load URL guide text
-                    parent.spawn(
-                        TextBundle::from_section(
-                            "Enter URL (after \"/?\"):",
-                            TextStyle {
-                                font: font.clone(),
-                                font_size: TEXT_SIZE,
-                                color: Color::GRAY,
-                            },
-                        )
-                        .with_style(Style {
-                            position_type: PositionType::Absolute,
-                            position: UiRect {
-                                bottom: Val::Px(225.0),
-                                left: Val::Px(170.0),
+                        SettingsView::Input,
+                    ))
+                    .with_children(|parent| {
+                        spawn_step_settings(
+                            parent,
+                            &StepSetting::INPUT,
+                            &settings,
+                            font.clone(),
+                            button_small_image.clone(),
+                        );
+
+                        // interrupt animation toggle button
+                        spawn_toggle_button(
+                            parent,
+                            UiRect {
+                                right: Val::Px(40.0),
+                                top: Val::Px(80.0 + 55.0 * StepSetting::INPUT.len() as f32),
                                 ..default()
                             },
-                            ..default()
-                        }),
-                    );
-
-                    // load URL text
-                    spawn_text_input_box(
-                        parent,
-                        UiRect {
-                            bottom: Val::Px(175.0),
-                            left: Val::Px(170.0),
+                            "Interrupt Animation".to_string(),
+                            font.clone(),
+                            MyButtonType::InterruptAnimation,
+                            match settings.interrupt_animation {
+                                true => button_toggle_on_image.clone(),
+                                false => button_toggle_off_image.clone(),
+                            },
+                        );
+
+                        // orientation assist arrows toggle button
+                        spawn_toggle_button(
+                            parent,
+                            UiRect {
+                                right: Val::Px(40.0),
+                                top: Val::Px(80.0 + 55.0 * (StepSetting::INPUT.len() + 1) as f32),
+                                ..default()
+                            },
+                            "Assist Arrows".to_string(),
+                            font.clone(),
+                            MyButtonType::AssistArrows,
+                            match settings.assist_arrows {
+                                true => button_toggle_on_image.clone(),
+                                false => button_toggle_off_image.clone(),
+                            },
+                        );
+                    });
+
+                // theme
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            visibility: Visibility::INVISIBLE,
                             ..default()
                         },
-                        Size::new(Val::Px(380.0), Val::Px(40.0)),
-                        font.clone(),
-                        12,
-                    );
-                }
+                        SettingsView::Theme,
+                    ))
+                    .with_children(|parent| {
+                        spawn_step_settings(
+                            parent,
+                            &StepSetting::THEME,
+                            &settings,
+                            font.clone(),
+                            button_small_image.clone(),
+                        );
+                    });
             });
         });
 }
 
+/// Rows of name, value, and buttons stepping the value
+fn spawn_step_settings(
+    parent: &mut ChildBuilder,
+    step_settings: &[StepSetting],
+    settings: &Settings,
+    font: Handle<Font>,
+    image: UiImage,
+) {
+    for (i, setting) in step_settings.iter().copied().enumerate() {
+        let top = 80.0 + 55.0 * i as f32;
+
+        // setting name and value
+        for (text, left, text_type) in [
+            (setting.name().to_string(), 40.0, None),
+            (
+                setting.value_to_string(settings),
+                230.0,
+                Some(StepSettingText(setting)),
+            ),
+        ] {
+            let bundle = TextBundle::from_section(
+                text,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: KEY_BINDING_TEXT_SIZE,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(left),
+                    top: Val::Px(top + 5.0),
+                    ..default()
+                },
+                ..default()
+            });
+            match text_type {
+                Some(text_type) => parent.spawn((bundle, text_type, ThemeColor::Text)),
+                None => parent.spawn((bundle, ThemeColor::Text)),
+            };
+        }
+
+        // increase and decrease buttons, previous and next for choices
+        let buttons = match setting.is_choice() {
+            true => [(false, "<", 95.0), (true, ">", 40.0)],
+            false => [(true, "+", 95.0), (false, "-", 40.0)],
+        };
+        for (increase, text, right) in buttons {
+            spawn_small_button(
+                parent,
+                UiRect {
+                    right: Val::Px(right),
+                    top: Val::Px(top),
+                    ..default()
+                },
+                Size::new(Val::Px(45.0), Val::Px(45.0)),
+                image.clone(),
+                StepSettingButton { setting, increase },
+                text.to_string(),
+                font.clone(),
+            );
+        }
+    }
+}
+
 fn spawn_toggle_button(
     parent: &mut ChildBuilder,
     position: UiRect,
@@ -203,7 +704,7 @@ fn spawn_toggle_button(
             button_type,
         ))
         .with_children(|parent| {
-            parent.spawn(
+            parent.spawn((
                 TextBundle::from_section(
                     text,
                     TextStyle {
@@ -221,6 +722,172 @@ fn spawn_toggle_button(
                     },
                     ..default()
                 }),
+                ThemeColor::Text,
+            ));
+        });
+}
+
+pub fn popup_system_settings(
+    mut view_button_query: Query<
+        (&Interaction, &mut BackgroundColor, &Children),
+        (Changed<Interaction>, With<Button>, With<SettingsViewButton>),
+    >,
+    mut key_binding_button_query: Query<
+        (&Interaction, &mut BackgroundColor, &KeyBindingButton),
+        (
+            Changed<Interaction>,
+            With<Button>,
+            Without<SettingsViewButton>,
+        ),
+    >,
+    mut step_button_query: Query<
+        (&Interaction, &mut BackgroundColor, &StepSettingButton),
+        (
+            Changed<Interaction>,
+            With<Button>,
+            Without<SettingsViewButton>,
+            Without<KeyBindingButton>,
+        ),
+    >,
+    mut view_query: Query<(&mut Visibility, &SettingsView)>,
+    mut text_query: Query<(
+        &mut Text,
+        Option<&KeyBindingText>,
+        Option<&KeyBindingStatusText>,
+        Option<&StepSettingText>,
+    )>,
+    mut key_capture: ResMut<KeyCapture>,
+    mut settings: ResMut<Settings>,
+    keyboard_input: Res<Input<KeyCode>>,
+) {
+    let button_color = settings.theme.ui.button_color();
+    for (interaction, mut color, children) in view_button_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                let current = view_query
+                    .iter()
+                    .find(|(visibility, _)| visibility.is_visible)
+                    .map_or(SettingsView::General, |(_, view)| *view);
+                let next = current.next();
+                for (mut visibility, view) in view_query.iter_mut() {
+                    visibility.is_visible = *view == next;
+                }
+                if let Ok((mut text, _, _, _)) = text_query.get_mut(children[0]) {
+                    text.sections[0].value = next.next().name().to_string();
+                }
+                key_capture.0 = None;
+                *color = (button_color * BUTTON_PRESS_MUL).into();
+            }
+            Interaction::Hovered => *color = (button_color * BUTTON_HOVER_MUL).into(),
+            Interaction::None => *color = button_color.into(),
+        }
+    }
+
+    let mut status = None;
+    for (interaction, mut color, button_type) in key_binding_button_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                match *button_type {
+                    KeyBindingButton::Add(action) => {
+                        key_capture.0 = Some(action);
+                        status = Some(format!("Press a key for {}", action.name()));
+                    }
+                    KeyBindingButton::Remove(action) => {
+                        settings.key_bindings.remove_last(action);
+                        key_capture.0 = None;
+                        status = Some(String::new());
+                    }
+                    KeyBindingButton::Reset => {
+                        settings.key_bindings = KeyBindings::default();
+                        key_capture.0 = None;
+                        status = Some(String::new());
+                    }
+                }
+                *color = (button_color * BUTTON_PRESS_MUL).into();
+            }
+            Interaction::Hovered => *color = (button_color * BUTTON_HOVER_MUL).into(),
+            Interaction::None => *color = button_color.into(),
+        }
+    }
+
+    for (interaction, mut color, button) in step_button_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                button.setting.step(&mut settings, button.increase);
+                *color = (button_color * BUTTON_PRESS_MUL).into();
+            }
+            Interaction::Hovered => *color = (button_color * BUTTON_HOVER_MUL).into(),
+            Interaction::None => *color = button_color.into(),
+        }
+    }
+
+    // bind the next key press, Esc is left to close the popup
+    if let Some(action) = key_capture.0 {
+        let pressed = keyboard_input
+            .get_just_pressed()
+            .find(|&&key| key != KeyCode::Escape);
+        if let Some(&key) = pressed {
+            status = Some(match settings.key_bindings.bind(action, key) {
+                Ok(()) => format!("{:?} is bound to {}", key, action.name()),
+                Err(bound_action) => {
+                    format!("{:?} is already bound to {}", key, bound_action.name())
+                }
+            });
+            key_capture.0 = None;
+        }
+    }
+
+    for (mut text, key_binding, status_text, step_setting) in text_query.iter_mut() {
+        if let Some(KeyBindingText(action)) = key_binding {
+            if settings.is_changed() {
+                text.sections[0].value = settings.key_bindings.keys_to_string(*action);
+            }
+        } else if let Some(StepSettingText(setting)) = step_setting {
+            if settings.is_changed() {
+                text.sections[0].value = setting.value_to_string(&settings);
+            }
+        } else if let (Some(status), Some(_)) = (&status, status_text) {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
+
+fn spawn_small_button(
+    parent: &mut ChildBuilder,
+    position: UiRect,
+    size: Size,
+    image: UiImage,
+    button_type: impl Component,
+    text: String,
+    font: Handle<Font>,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    position_type: PositionType::Absolute,
+                    position,
+                    size,
+                    ..default()
+                },
+                image,
+                ..default()
+            },
+            button_type,
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        font,
+                        font_size: KEY_BINDING_TEXT_SIZE,
+                        color: Color::BLACK,
+                    },
+                )
+                .with_text_alignment(TextAlignment::CENTER),
             );
         });
 }
@@ -1,6 +1,15 @@
-use crate::{statistics_manager::StatisticsManager, ui::*};
+use crate::{
+    settings::Settings,
+    statistics_manager::{AverageType, StatisticsManager},
+    theme::ThemeColor,
+    ui::*,
+};
 
-const SCROLL_BAR_MAX_ITEMS: usize = 11;
+const SCROLL_BAR_MAX_ITEMS: usize = 10;
+const VIEW_TEXT_SIZE: f32 = 25.0;
+const VIEW_BUTTON_TEXT_SIZE: f32 = 20.0;
+const STATISTICS_TEXT_SIZE: f32 = 32.0;
+const SESSION_NAME_MAX_CHARS: usize = 10;
 
 #[derive(Default)]
 pub struct DeleteStatisticsEvent;
@@ -18,7 +27,14 @@ pub enum PopupStatisticsTextType {
     Average,
     Best,
     Worst,
+}
+
+/// Views of the right side of the statistics popup, and the buttons selecting them
+#[derive(Component, PartialEq, Eq, Clone, Copy)]
+pub enum StatisticsView {
     Details,
+    Averages,
+    Chart,
 }
 
 pub fn spawn_popup_statistics(
@@ -36,9 +52,9 @@ pub fn spawn_popup_statistics(
         .with_children(|parent| {
             spawn_popup_panel(parent, button_close_image.clone(), font.clone(), |parent| {
                 // statistics text
-                parent.spawn(
+                parent.spawn((
                     TextBundle::from_section(
-                        "Statistics",
+                        format!("Statistics {}", statistics_manager.config_to_string()),
                         TextStyle {
                             font: font.clone(),
                             font_size: TEXT_SIZE,
@@ -52,13 +68,76 @@ pub fn spawn_popup_statistics(
                         },
                         ..default()
                     }),
-                );
+                    ThemeColor::Text,
+                ));
+
+                // session select buttons and name
+                let button_small_image =
+                    UiImage::from(asset_server.load("images/button_small.png"));
+                for (button_type, text, left, width) in [
+                    (MyButtonType::PrevSession, "<", 25.0, 40.0),
+                    (MyButtonType::NextSession, ">", 215.0, 40.0),
+                    (MyButtonType::NewSession, "+", 260.0, 40.0),
+                ] {
+                    spawn_view_button(
+                        parent,
+                        UiRect {
+                            top: Val::Px(80.0),
+                            left: Val::Px(left),
+                            ..default()
+                        },
+                        Size::new(Val::Px(width), Val::Px(45.0)),
+                        button_small_image.clone(),
+                        button_type,
+                        text.to_string(),
+                        font.clone(),
+                    );
+                }
+                let session = statistics_manager.session();
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            position: UiRect {
+                                top: Val::Px(80.0),
+                                left: Val::Px(70.0),
+                                ..default()
+                            },
+                            size: Size::new(Val::Px(140.0), Val::Px(45.0)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section(
+                                match session.chars().count() > SESSION_NAME_MAX_CHARS {
+                                    true => {
+                                        session
+                                            .chars()
+                                            .take(SESSION_NAME_MAX_CHARS - 2)
+                                            .collect::<String>()
+                                            + ".."
+                                    }
+                                    false => session.to_string(),
+                                },
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: VIEW_TEXT_SIZE,
+                                    color: Color::WHITE,
+                                },
+                            ),
+                            ThemeColor::Text,
+                        ));
+                    });
 
                 // number of solves
                 spawn_text(
                     parent,
                     UiRect {
-                        top: Val::Px(80.0),
+                        top: Val::Px(135.0),
                         left: Val::Px(25.0),
                         ..default()
                     },
@@ -67,31 +146,42 @@ pub fn spawn_popup_statistics(
                     Some(PopupStatisticsTextType::Solves),
                 );
 
-                // export button
-                spawn_image_button(
-                    parent,
-                    UiRect {
-                        bottom: Val::Px(25.0),
-                        left: Val::Px(25.0),
-                        ..default()
-                    },
-                    MyButtonType::Export,
-                    asset_server.load("images/button_share.png").into(),
-                    "Copy to clipboard".to_string(),
-                    font.clone(),
-                );
+                // export and import buttons
+                for (i, (button_type, text)) in [
+                    (MyButtonType::Export, "Text"),
+                    (MyButtonType::ExportCsv, "CSV"),
+                    (MyButtonType::ExportJson, "JSON"),
+                    (MyButtonType::ImportStatistics, "Import"),
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    spawn_view_button(
+                        parent,
+                        UiRect {
+                            bottom: Val::Px(80.0 - 55.0 * (i / 2) as f32),
+                            left: Val::Px(135.0 + 85.0 * (i % 2) as f32),
+                            ..default()
+                        },
+                        Size::new(Val::Px(80.0), Val::Px(45.0)),
+                        button_small_image.clone(),
+                        button_type,
+                        text.to_string(),
+                        font.clone(),
+                    );
+                }
 
                 // delete statistics button
                 spawn_image_button(
                     parent,
                     UiRect {
                         bottom: Val::Px(25.0),
-                        left: Val::Px(145.0),
+                        left: Val::Px(25.0),
                         ..default()
                     },
                     MyButtonType::DeleteStatistics,
                     asset_server.load("images/button_delete.png").into(),
-                    "Delete all".to_string(),
+                    "Delete session".to_string(),
                     font.clone(),
                 );
 
@@ -100,7 +190,7 @@ pub fn spawn_popup_statistics(
                     spawn_text(
                         parent,
                         UiRect {
-                            top: Val::Px(180.0),
+                            top: Val::Px(220.0),
                             left: Val::Px(25.0),
                             ..default()
                         },
@@ -113,7 +203,7 @@ pub fn spawn_popup_statistics(
                     spawn_text(
                         parent,
                         UiRect {
-                            top: Val::Px(280.0),
+                            top: Val::Px(305.0),
                             left: Val::Px(25.0),
                             ..default()
                         },
@@ -126,7 +216,7 @@ pub fn spawn_popup_statistics(
                     spawn_text(
                         parent,
                         UiRect {
-                            top: Val::Px(380.0),
+                            top: Val::Px(390.0),
                             left: Val::Px(25.0),
                             ..default()
                         },
@@ -135,18 +225,29 @@ pub fn spawn_popup_statistics(
                         Some(PopupStatisticsTextType::Worst),
                     );
 
-                    // details (text)
-                    spawn_text(
-                        parent,
-                        UiRect {
-                            top: Val::Px(80.0),
-                            left: Val::Px(325.0),
-                            ..default()
-                        },
-                        "Details".to_string(),
-                        font.clone(),
-                        Some(PopupStatisticsTextType::Details),
-                    );
+                    // view select buttons
+                    for (i, (view, text)) in [
+                        (StatisticsView::Details, "Details"),
+                        (StatisticsView::Averages, "Averages"),
+                        (StatisticsView::Chart, "Chart"),
+                    ]
+                    .into_iter()
+                    .enumerate()
+                    {
+                        spawn_view_button(
+                            parent,
+                            UiRect {
+                                top: Val::Px(80.0),
+                                right: Val::Px(215.0 - 95.0 * i as f32),
+                                ..default()
+                            },
+                            Size::new(Val::Px(85.0), Val::Px(50.0)),
+                            button_small_image.clone(),
+                            view,
+                            text.to_string(),
+                            font.clone(),
+                        );
+                    }
 
                     let mut content = vec![];
                     for i in 0..statistics_manager.solves() {
@@ -156,7 +257,7 @@ pub fn spawn_popup_statistics(
                     // scroll bar background
                     spawn_scroll_bar(
                         parent,
-                        Size::new(Val::Px(275.0), Val::Px(450.0)),
+                        Size::new(Val::Px(275.0), Val::Px(430.0)),
                         UiRect {
                             right: Val::Px(25.0),
                             bottom: Val::Px(25.0),
@@ -166,8 +267,126 @@ pub fn spawn_popup_statistics(
                         SCROLL_BAR_MAX_ITEMS,
                         font.clone(),
                         true,
-                        None::<Node>, // 아무 타입이든 지정하긴 해야 되네
+                        Some(StatisticsView::Details),
                     );
+
+                    // rolling averages, current and best
+                    parent
+                        .spawn((
+                            NodeBundle {
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    size: Size::new(Val::Px(275.0), Val::Px(430.0)),
+                                    position: UiRect {
+                                        right: Val::Px(25.0),
+                                        bottom: Val::Px(25.0),
+                                        ..default()
+                                    },
+                                    ..default()
+                                },
+                                visibility: Visibility::INVISIBLE,
+                                background_color: Color::rgb(0.2, 0.2, 0.2).into(),
+                                ..default()
+                            },
+                            StatisticsView::Averages,
+                            ThemeColor::Panel,
+                        ))
+                        .with_children(|parent| {
+                            let mut columns = [
+                                vec![String::new()],
+                                vec!["Current".to_string()],
+                                vec!["Best".to_string()],
+                            ];
+                            for average_type in AverageType::ALL {
+                                columns[0].push(average_type.name().to_string());
+                                columns[1].push(statistics_manager.current_average(average_type));
+                                columns[2].push(statistics_manager.best_average(average_type));
+                            }
+                            for (column, left) in columns.iter().zip([15.0, 80.0, 180.0]) {
+                                parent.spawn((
+                                    TextBundle::from_section(
+                                        column.join("\n"),
+                                        TextStyle {
+                                            font: font.clone(),
+                                            font_size: VIEW_TEXT_SIZE,
+                                            color: Color::WHITE,
+                                        },
+                                    )
+                                    .with_style(Style {
+                                        position_type: PositionType::Absolute,
+                                        position: UiRect {
+                                            top: Val::Px(15.0),
+                                            left: Val::Px(left),
+                                            ..default()
+                                        },
+                                        ..default()
+                                    }),
+                                    ThemeColor::Text,
+                                ));
+                            }
+                        });
+
+                    // chart of solves
+                    parent
+                        .spawn((
+                            NodeBundle {
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    size: Size::new(Val::Px(275.0), Val::Px(430.0)),
+                                    position: UiRect {
+                                        right: Val::Px(25.0),
+                                        bottom: Val::Px(25.0),
+                                        ..default()
+                                    },
+                                    ..default()
+                                },
+                                visibility: Visibility::INVISIBLE,
+                                background_color: Color::rgb(0.2, 0.2, 0.2).into(),
+                                ..default()
+                            },
+                            StatisticsView::Chart,
+                            ThemeColor::Panel,
+                        ))
+                        .with_children(|parent| {
+                            parent
+                                .spawn((
+                                    NodeBundle {
+                                        style: Style {
+                                            position_type: PositionType::Absolute,
+                                            size: Size::new(
+                                                Val::Percent(100.0),
+                                                Val::Percent(100.0),
+                                            ),
+                                            ..default()
+                                        },
+                                        ..default()
+                                    },
+                                    StatisticsChart,
+                                ))
+                                .with_children(|parent| {
+                                    spawn_chart(
+                                        parent,
+                                        statistics_manager,
+                                        ChartAxis::SolveIndex,
+                                        font.clone(),
+                                    );
+                                });
+
+                            // axis toggle button
+                            spawn_view_button(
+                                parent,
+                                UiRect {
+                                    bottom: Val::Px(10.0),
+                                    left: Val::Px(72.5),
+                                    ..default()
+                                },
+                                Size::new(Val::Px(130.0), Val::Px(40.0)),
+                                button_small_image.clone(),
+                                ChartAxis::SolveIndex,
+                                ChartAxis::SolveIndex.name().to_string(),
+                                font.clone(),
+                            );
+                        });
                 }
             });
         });
@@ -175,23 +394,81 @@ pub fn spawn_popup_statistics(
 
 pub fn popup_system_statistics(
     mut commands: Commands,
-    scroll_bar_query: Query<(&mut ScrollBar, &Children, Entity)>,
     mut statistics_text_query: Query<(&mut Text, &PopupStatisticsTextType, Entity)>,
     delete_statistics_event: EventReader<DeleteStatisticsEvent>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &StatisticsView),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut view_query: Query<(&mut Visibility, &StatisticsView, Entity), Without<Button>>,
+    view_button_query: Query<Entity, (With<StatisticsView>, With<Button>)>,
+    mut axis_button_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &mut ChartAxis,
+            &Children,
+        ),
+        (Changed<Interaction>, With<Button>, Without<StatisticsView>),
+    >,
+    mut button_text_query: Query<&mut Text, Without<PopupStatisticsTextType>>,
+    chart_query: Query<(Entity, &Children), With<StatisticsChart>>,
+    statistics_manager_query: Query<&StatisticsManager>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
 ) {
+    let button_color = settings.theme.ui.button_color();
+    for (interaction, mut color, button_view) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                for (mut visibility, view, _) in view_query.iter_mut() {
+                    visibility.is_visible = *view == *button_view;
+                }
+                *color = (button_color * BUTTON_PRESS_MUL).into();
+            }
+            Interaction::Hovered => *color = (button_color * BUTTON_HOVER_MUL).into(),
+            Interaction::None => *color = button_color.into(),
+        }
+    }
+
+    for (interaction, mut color, mut axis, children) in axis_button_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                *axis = axis.toggled();
+                button_text_query.get_mut(children[0]).unwrap().sections[0].value =
+                    axis.name().to_string();
+                if let Ok((chart, chart_children)) = chart_query.get_single() {
+                    for &child in chart_children {
+                        commands.entity(child).despawn_recursive();
+                    }
+                    let font = asset_server.load("fonts/Quicksand-Bold.ttf");
+                    let axis = *axis;
+                    commands.entity(chart).with_children(|parent| {
+                        spawn_chart(parent, statistics_manager_query.single(), axis, font);
+                    });
+                }
+                *color = (button_color * BUTTON_PRESS_MUL).into();
+            }
+            Interaction::Hovered => *color = (button_color * BUTTON_HOVER_MUL).into(),
+            Interaction::None => *color = button_color.into(),
+        }
+    }
+
     if !delete_statistics_event.is_empty() {
         for (mut text, text_type, entity) in statistics_text_query.iter_mut() {
             match text_type {
                 PopupStatisticsTextType::Solves => text.sections[0].value = "Solves\n0".to_string(),
                 PopupStatisticsTextType::Average
                 | PopupStatisticsTextType::Best
-                | PopupStatisticsTextType::Worst
-                | PopupStatisticsTextType::Details => {
+                | PopupStatisticsTextType::Worst => {
                     commands.entity(entity).despawn_recursive();
                 }
             }
         }
-        if let Ok((_, _, entity)) = scroll_bar_query.get_single() {
+        for (_, _, entity) in view_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        for entity in view_button_query.iter() {
             commands.entity(entity).despawn_recursive();
         }
     }
@@ -208,7 +485,7 @@ fn spawn_text(
         text,
         TextStyle {
             font,
-            font_size: TEXT_SIZE,
+            font_size: STATISTICS_TEXT_SIZE,
             color: Color::WHITE,
         },
     )
@@ -218,8 +495,48 @@ fn spawn_text(
         ..default()
     });
     if let Some(text_type) = text_type {
-        parent.spawn((text_bundle, text_type));
+        parent.spawn((text_bundle, text_type, ThemeColor::Text));
     } else {
-        parent.spawn(text_bundle);
+        parent.spawn((text_bundle, ThemeColor::Text));
     };
 }
+
+fn spawn_view_button(
+    parent: &mut ChildBuilder,
+    position: UiRect,
+    size: Size,
+    image: UiImage,
+    button_type: impl Component,
+    text: String,
+    font: Handle<Font>,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    position_type: PositionType::Absolute,
+                    position,
+                    size,
+                    ..default()
+                },
+                image,
+                ..default()
+            },
+            button_type,
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        font,
+                        font_size: VIEW_BUTTON_TEXT_SIZE,
+                        color: Color::BLACK,
+                    },
+                )
+                .with_text_alignment(TextAlignment::CENTER),
+            );
+        });
+}
@@ -1,4 +1,4 @@
-use crate::ui::*;
+use crate::{theme::ThemeColor, ui::*};
 use bevy::{
     input::mouse::{MouseScrollUnit, MouseWheel},
     prelude::*,
@@ -14,7 +14,7 @@ pub fn spawn_scroll_bar(
     max_items: usize,
     font: Handle<Font>,
     visible: bool,
-    extra_component: Option<impl Component>,
+    extra_component: Option<impl Bundle>,
 ) {
     let node_bundle = NodeBundle {
         style: Style {
@@ -37,12 +37,12 @@ pub fn spawn_scroll_bar(
         position: 0.0,
     };
     match extra_component {
-        Some(component) => parent.spawn((node_bundle, scroll_bar, component)),
-        None => parent.spawn((node_bundle, scroll_bar)),
+        Some(component) => parent.spawn((node_bundle, scroll_bar, ThemeColor::Panel, component)),
+        None => parent.spawn((node_bundle, scroll_bar, ThemeColor::Panel)),
     }
     .with_children(|parent| {
         for i in 0..max_items {
-            parent.spawn(
+            parent.spawn((
                 TextBundle::from_section(
                     String::new(),
                     TextStyle {
@@ -60,7 +60,8 @@ pub fn spawn_scroll_bar(
                     },
                     ..default()
                 }),
-            );
+                ThemeColor::Text,
+            ));
         }
     });
 }
@@ -0,0 +1,227 @@
+use crate::{
+    statistics_manager::{AverageType, StatisticsManager},
+    ui::*,
+};
+
+const CHART_MAX_SOLVES: usize = 200;
+const CHART_TEXT_SIZE: f32 = 20.0;
+
+// plot area inside the chart node
+const PLOT_LEFT: f32 = 15.0;
+const PLOT_TOP: f32 = 70.0;
+const PLOT_WIDTH: f32 = 245.0;
+const PLOT_HEIGHT: f32 = 270.0;
+
+const SOLVE_COLOR: Color = Color::rgb(0.7, 0.7, 0.7);
+const PERSONAL_BEST_COLOR: Color = Color::rgb(1.0, 0.8, 0.2);
+const AVERAGE_SERIES: [(AverageType, Color); 2] = [
+    (AverageType::Ao5, Color::rgb(0.3, 0.9, 0.3)),
+    (AverageType::Ao12, Color::rgb(0.3, 0.6, 1.0)),
+];
+
+/// Horizontal axis of the chart, also the button toggling it
+#[derive(Component, PartialEq, Eq, Clone, Copy)]
+pub enum ChartAxis {
+    SolveIndex,
+    Date,
+}
+
+impl ChartAxis {
+    pub fn toggled(&self) -> Self {
+        match self {
+            ChartAxis::SolveIndex => ChartAxis::Date,
+            ChartAxis::Date => ChartAxis::SolveIndex,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChartAxis::SolveIndex => "By solve",
+            ChartAxis::Date => "By date",
+        }
+    }
+}
+
+/// Node holding the chart, its children are rebuilt when the axis changes
+#[derive(Component)]
+pub struct StatisticsChart;
+
+/// Plot the latest `CHART_MAX_SOLVES` solves of the current configuration with rolling averages.
+/// Personal bests are marked, and solves without timestamp are skipped on the date axis.
+pub fn spawn_chart(
+    parent: &mut ChildBuilder,
+    statistics_manager: &StatisticsManager,
+    axis: ChartAxis,
+    font: Handle<Font>,
+) {
+    let values = statistics_manager.values();
+    let timestamps = statistics_manager.timestamps();
+    let start = values.len().saturating_sub(CHART_MAX_SOLVES);
+
+    // horizontal position of each solve in 0.0 ~ 1.0
+    let xs: Vec<Option<f32>> = match axis {
+        ChartAxis::SolveIndex => {
+            let last = (values.len() - start).saturating_sub(1).max(1) as f32;
+            (0..values.len())
+                .map(|i| (i >= start).then(|| (i - start) as f32 / last))
+                .collect()
+        }
+        ChartAxis::Date => {
+            let dated = timestamps[start..].iter().flatten();
+            match (dated.clone().min(), dated.max()) {
+                (Some(&first), Some(&last)) => {
+                    let range = (last - first).num_seconds().max(1) as f32;
+                    timestamps
+                        .iter()
+                        .enumerate()
+                        .map(|(i, timestamp)| {
+                            timestamp
+                                .filter(|_| i >= start)
+                                .map(|timestamp| (timestamp - first).num_seconds() as f32 / range)
+                        })
+                        .collect()
+                }
+                _ => vec![None; values.len()],
+            }
+        }
+    };
+
+    // series of (points, color, size), personal bests are drawn last to be on top
+    let mut personal_best = f64::INFINITY;
+    let mut solves = vec![];
+    let mut personal_bests = vec![];
+    for (i, &value) in values.iter().enumerate() {
+        let is_personal_best = value < personal_best;
+        personal_best = personal_best.min(value);
+        if let Some(x) = xs[i] {
+            match is_personal_best {
+                true => personal_bests.push((x, value)),
+                false => solves.push((x, value)),
+            }
+        }
+    }
+    let mut series = vec![(solves, SOLVE_COLOR, 4.0)];
+    for (average_type, color) in AVERAGE_SERIES {
+        let averages = statistics_manager
+            .rolling_averages(average_type)
+            .iter()
+            .enumerate()
+            .filter_map(|(i, average)| Some((xs[i]?, (*average)?)))
+            .collect();
+        series.push((averages, color, 3.0));
+    }
+    series.push((personal_bests, PERSONAL_BEST_COLOR, 7.0));
+
+    let text_style = |color| TextStyle {
+        font: font.clone(),
+        font_size: CHART_TEXT_SIZE,
+        color,
+    };
+
+    // legend
+    let mut legend = vec![TextSection::new("solve  ", text_style(SOLVE_COLOR))];
+    for (average_type, color) in AVERAGE_SERIES {
+        legend.push(TextSection::new(
+            format!("{}  ", average_type.name()),
+            text_style(color),
+        ));
+    }
+    legend.push(TextSection::new("PB", text_style(PERSONAL_BEST_COLOR)));
+    parent.spawn(TextBundle::from_sections(legend).with_style(Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            top: Val::Px(10.0),
+            left: Val::Px(PLOT_LEFT),
+            ..default()
+        },
+        ..default()
+    }));
+
+    let plotted = series
+        .iter()
+        .flat_map(|(points, _, _)| points.iter().map(|&(_, value)| value));
+    let (min, max) = match (plotted.clone().reduce(f64::min), plotted.reduce(f64::max)) {
+        (Some(min), Some(max)) => (min, max),
+        _ => {
+            spawn_chart_text(
+                parent,
+                UiRect {
+                    top: Val::Px(PLOT_TOP + PLOT_HEIGHT / 2.0),
+                    left: Val::Px(PLOT_LEFT),
+                    ..default()
+                },
+                "No solves to plot".to_string(),
+                text_style(Color::WHITE),
+            );
+            return;
+        }
+    };
+    let range = (max - min).max(f64::EPSILON);
+
+    // value labels of the top and bottom of the plot
+    spawn_chart_text(
+        parent,
+        UiRect {
+            top: Val::Px(PLOT_TOP - 30.0),
+            left: Val::Px(PLOT_LEFT),
+            ..default()
+        },
+        statistics_manager.value_to_string(max),
+        text_style(Color::WHITE),
+    );
+    spawn_chart_text(
+        parent,
+        UiRect {
+            top: Val::Px(PLOT_TOP + PLOT_HEIGHT + 5.0),
+            left: Val::Px(PLOT_LEFT),
+            ..default()
+        },
+        statistics_manager.value_to_string(min),
+        text_style(Color::WHITE),
+    );
+
+    // plot background
+    parent.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(PLOT_TOP),
+                left: Val::Px(PLOT_LEFT),
+                ..default()
+            },
+            size: Size::new(Val::Px(PLOT_WIDTH), Val::Px(PLOT_HEIGHT)),
+            ..default()
+        },
+        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+        ..default()
+    });
+
+    // points, lower values are better and drawn lower
+    for (points, color, size) in series {
+        for (x, value) in points {
+            let y = ((value - min) / range) as f32;
+            parent.spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(PLOT_LEFT + x * PLOT_WIDTH - size / 2.0),
+                        top: Val::Px(PLOT_TOP + (1.0 - y) * PLOT_HEIGHT - size / 2.0),
+                        ..default()
+                    },
+                    size: Size::new(Val::Px(size), Val::Px(size)),
+                    ..default()
+                },
+                background_color: color.into(),
+                ..default()
+            });
+        }
+    }
+}
+
+fn spawn_chart_text(parent: &mut ChildBuilder, position: UiRect, text: String, style: TextStyle) {
+    parent.spawn(TextBundle::from_section(text, style).with_style(Style {
+        position_type: PositionType::Absolute,
+        position,
+        ..default()
+    }));
+}
@@ -73,4 +73,6 @@ pub fn string_to_board(
     });
 
     game.is_shuffled = true;
+    game.initial_board = Some(board_string.clone());
+    game.leaderboard_attempt = None;
 }